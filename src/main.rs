@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::*;
 use std::path::{Path, PathBuf};
 
@@ -26,12 +26,15 @@ struct Cli {
 enum Commands {
     /// Delete everything in CWD except listed items  [e.g. vasu del .git vasu]
     Del {
-        /// Items to KEEP (everything else gets deleted)
+        /// Items to KEEP, names or globs (everything else gets deleted)
         #[arg(required = true, num_args = 1..)]
         keep: Vec<String>,
         /// Skip confirmation prompt
         #[arg(short, long)]
         yes: bool,
+        /// Match keep patterns case-sensitively
+        #[arg(short = 's', long)]
+        case_sensitive: bool,
     },
 
     /// Deep-copy SRC → DST (hidden files, permissions, symlinks)
@@ -50,9 +53,12 @@ enum Commands {
         /// Files, globs, or dirs to copy. Use * for everything recursively.
         #[arg(num_args = 0..)]
         targets: Vec<String>,
-        /// No file-name headers between files
+        /// No file-name headers between files  [config: cb.no_header]
         #[arg(long)]
         no_header: bool,
+        /// Match globs case-sensitively
+        #[arg(short = 's', long)]
+        case_sensitive: bool,
     },
 
     /// Pretty directory tree
@@ -60,25 +66,60 @@ enum Commands {
         /// Root directory (default: current)
         #[arg(default_value = ".")]
         directory: PathBuf,
-        /// Max depth
-        #[arg(short, long, default_value = "4")]
-        depth: usize,
-        /// Show hidden files
+        /// Max depth  [default: 4, config: tree.depth]
+        #[arg(short, long)]
+        depth: Option<usize>,
+        /// Show hidden files  [config: tree.all]
         #[arg(short, long)]
         all: bool,
     },
 
-    /// Find files by name pattern (supports * wildcards)
+    /// Find files by glob (*, ?, [abc], {a,b}, **)
     Find {
-        /// Pattern to match (e.g. "*.rs")
+        /// Pattern to match (e.g. "*.rs", "src/**/mod.rs", "main.rs"); a plain name matches exactly
         pattern: String,
         /// Root directory
         #[arg(default_value = ".")]
         directory: PathBuf,
-        /// Filter: f=files, d=dirs
-        #[arg(short = 't', long, default_value = "all",
+        /// Filter: f=files, d=dirs  [default: all, config: find.type]
+        #[arg(short = 't', long,
               value_parser = ["f","d","all"])]
-        ftype: String,
+        ftype: Option<String>,
+        /// Match case-sensitively
+        #[arg(short = 's', long)]
+        case_sensitive: bool,
+        /// Find every name containing PATTERN instead of matching it exactly
+        #[arg(long)]
+        substring: bool,
+        #[command(flatten)]
+        filters: FindFilters,
+        #[command(flatten)]
+        actions: FindActions,
+    },
+
+    /// Search file contents with a regex (respects .gitignore)
+    Grep {
+        /// Regular expression (or text with -F)
+        #[arg(required_unless_present = "type_list")]
+        pattern: Option<String>,
+        /// Files or folders to search
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+        #[command(flatten)]
+        opts: GrepOpts,
+    },
+
+    /// Search and replace in file contents, with a diff preview
+    Replace {
+        /// Regular expression (or text with -F)
+        pattern: String,
+        /// Replacement; $1, ${name} insert capture groups ($$ for a literal $)
+        replacement: String,
+        /// Files or folders to edit
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+        #[command(flatten)]
+        opts: ReplaceOpts,
     },
 
     /// Disk usage per item, sorted by size
@@ -86,9 +127,34 @@ enum Commands {
         /// Root directory
         #[arg(default_value = ".")]
         directory: PathBuf,
-        /// Show top N items
-        #[arg(short = 'n', long, default_value = "20")]
-        top: usize,
+        /// Show top N items  [default: 20, config: size.top]
+        #[arg(short = 'n', long)]
+        top: Option<usize>,
+        /// Nest N levels deep, with bar charts
+        #[arg(short, long, default_value_t = 1)]
+        depth: usize,
+        /// Total files by extension, age, owner or depth instead of by folder
+        #[arg(long, value_parser = ["ext", "age", "owner", "depth"], conflicts_with_all = ["depth", "interactive"])]
+        by: Option<String>,
+        /// List the N largest files anywhere below the directory
+        #[arg(long, group = "largest", conflicts_with_all = ["depth", "by", "interactive", "dirs"])]
+        files: bool,
+        /// List the N largest folders anywhere below the directory
+        #[arg(long, group = "largest", conflicts_with_all = ["depth", "by", "interactive"])]
+        dirs: bool,
+        /// With --files / --dirs: skip anything smaller than this (e.g. 100M)
+        #[arg(long, value_parser = parse_size, requires = "largest")]
+        min_size: Option<u64>,
+        /// With --files: only these extensions, comma-separated
+        #[arg(short, long, value_delimiter = ',', requires = "files", conflicts_with = "dirs")]
+        ext: Vec<String>,
+        /// Browse interactively: drill into folders, sort, delete or trash
+        #[arg(short, long)]
+        interactive: bool,
+        #[command(flatten)]
+        opts: SizeOpts,
+        #[command(flatten)]
+        history: SizeHistoryArgs,
     },
 
     /// Remove build artifacts and junk files
@@ -99,6 +165,18 @@ enum Commands {
         /// Skip confirmation
         #[arg(short, long)]
         yes: bool,
+        /// Presets to apply, comma-separated (rust,node,python,java,jvm,go) or "auto"  [config: clean.presets]
+        #[arg(short, long, value_delimiter = ',')]
+        preset: Vec<String>,
+        /// Show every preset and rule, then exit
+        #[arg(long)]
+        list_rules: bool,
+        /// Only remove items untouched for this long (30d, 12h, 2w, 6mo, 1y)
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<std::time::Duration>,
+        /// Find every project under the directory and pick which ones to clean
+        #[arg(short, long, conflicts_with = "list_rules")]
+        scan: bool,
     },
 
     /// Zip a file or folder
@@ -107,6 +185,8 @@ enum Commands {
         source: PathBuf,
         /// Output zip file (optional)
         output: Option<PathBuf>,
+        #[command(flatten)]
+        opts: ZipArgs,
     },
 
     /// Unzip an archive
@@ -116,20 +196,44 @@ enum Commands {
         /// Destination folder
         #[arg(default_value = ".")]
         destination: PathBuf,
+        /// Read the password for encrypted archives from a file
+        #[arg(long, value_name = "FILE")]
+        password_file: Option<PathBuf>,
+    },
+
+    /// List the contents of a zip or tar archive as a tree
+    LsArchive {
+        /// Archive file (.zip, .tar, .tar.gz, .tgz)
+        archive: PathBuf,
+        /// Max depth
+        #[arg(short, long, default_value = "32")]
+        depth: usize,
+    },
+
+    /// Print a single archive entry to stdout without extracting
+    CatArchive {
+        /// Archive file (.zip, .tar, .tar.gz, .tgz)
+        archive: PathBuf,
+        /// Path of the entry inside the archive
+        entry: String,
     },
 
-    /// Bulk rename files: replace PATTERN with REPLACEMENT
+    /// Bulk rename files: replace PATTERN with REPLACEMENT, apply a template / case style, or --edit
     Rename {
-        /// String to find in filenames
-        pattern: String,
-        /// String to replace with
-        replacement: String,
-        /// Directory to scan
-        #[arg(default_value = ".")]
-        directory: PathBuf,
-        /// Preview only, don't rename
-        #[arg(short = 'n', long)]
-        dry_run: bool,
+        /// Text (or regex with -r) to find in names; only matching files are renamed
+        #[arg(required_unless_present_any = ["template", "lower", "snake", "kebab", "edit"])]
+        pattern: Option<String>,
+        /// Replacement; may use {stem} {ext} {n:03} {date} … tokens
+        #[arg(required_unless_present_any = ["template", "lower", "snake", "kebab", "edit"])]
+        replacement: Option<String>,
+        /// Directory to scan [default: .]
+        #[arg(value_name = "DIR")]
+        path: Option<PathBuf>,
+        /// Directory to scan, when it can't be given as the last argument
+        #[arg(short = 'C', long = "dir", conflicts_with = "path")]
+        directory: Option<PathBuf>,
+        #[command(flatten)]
+        opts: RenameOpts,
     },
 
     /// Count files and total lines of code
@@ -148,14 +252,28 @@ enum Commands {
         file: PathBuf,
     },
 
-    /// Create a timestamped zip backup
+    /// Create a timestamped zip backup, or an incremental one with -i
+    #[command(args_conflicts_with_subcommands = true)]
     Backup {
+        #[command(subcommand)]
+        action: Option<BackupAction>,
         /// Source to back up
         #[arg(default_value = ".")]
         source: PathBuf,
-        /// Where to place the backup
-        #[arg(short, long, default_value = ".")]
-        dest: PathBuf,
+        /// Where to place the backup (the repository directory with -i)  [default: ., config: backup.dest]
+        #[arg(short, long)]
+        dest: Option<PathBuf>,
+        /// Incremental: store deduplicated chunks in a repository at DEST
+        #[arg(short, long, conflicts_with_all = ["encrypt", "reproducible", "store"])]
+        incremental: bool,
+        /// Run a named profile from vasu.toml instead of SOURCE/DEST
+        #[arg(short, long, conflicts_with_all = ["source", "dest", "incremental"])]
+        profile: Option<String>,
+        /// Run every profile defined in vasu.toml
+        #[arg(long, conflicts_with_all = ["source", "dest", "incremental", "profile"])]
+        all_profiles: bool,
+        #[command(flatten)]
+        opts: ZipArgs,
     },
 
     /// Print environment variables (optionally filtered)
@@ -167,9 +285,8 @@ enum Commands {
 
     /// Spin up a quick HTTP file server
     Http {
-        /// Port number
-        #[arg(default_value = "8080")]
-        port: u16,
+        /// Port number  [default: 8080, config: http.port]
+        port: Option<u16>,
         /// Directory to serve
         #[arg(default_value = ".")]
         directory: PathBuf,
@@ -189,6 +306,167 @@ enum Commands {
         #[arg(default_value = ".")]
         directory: PathBuf,
     },
+
+    /// Show or edit configuration (~/.config/vasu/config.toml, .vasu.toml)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective value of a key (e.g. tree.depth)
+    Get {
+        key: String,
+    },
+    /// Set a key in the global config (or the project's .vasu.toml with --project)
+    Set {
+        key: String,
+        /// TOML value: 6, true, "text", ["a", "b"] — bare words are taken as strings
+        value: String,
+        /// Write to .vasu.toml (nearest one upward, else in the current directory)
+        #[arg(short, long)]
+        project: bool,
+    },
+    /// List every effective setting and where it comes from
+    List,
+    /// Show the config file locations
+    Path,
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// List snapshots in an incremental backup repository
+    List {
+        /// Repository directory
+        #[arg(default_value = ".")]
+        repo: PathBuf,
+    },
+
+    /// Restore a snapshot (or one path inside it) from a repository
+    Restore {
+        /// Snapshot id, unique id prefix, or "latest"
+        id: String,
+        /// Only restore this file or directory (relative to the backed-up source)
+        path: Option<String>,
+        /// Repository directory
+        #[arg(short, long, default_value = ".")]
+        repo: PathBuf,
+        /// Restore into this directory (default: ./<source name>)
+        #[arg(short, long)]
+        to: Option<PathBuf>,
+        /// Overwrite files that already exist
+        #[arg(short, long)]
+        overwrite: bool,
+    },
+
+    /// Re-read a zip backup or repository and check CRCs / SHA-256 manifests
+    Verify {
+        /// Backup zip or incremental repository
+        target: PathBuf,
+        /// Also compare against the live source and report drift
+        #[arg(short, long)]
+        source: Option<PathBuf>,
+        /// Repository snapshot to verify (default: all; drift uses the latest)
+        #[arg(long)]
+        id: Option<String>,
+        /// Read the password for encrypted archives from a file
+        #[arg(long, value_name = "FILE")]
+        password_file: Option<PathBuf>,
+    },
+
+    /// Delete old backups according to a retention policy
+    Prune {
+        /// Folder of timestamped zips, or an incremental repository
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        #[command(flatten)]
+        policy: RetentionArgs,
+        /// Preview only, don't delete
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Skip confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// How `vasu size` counts bytes
+#[derive(Args, Clone, Copy, Default)]
+struct SizeOpts {
+    /// Count file lengths instead of allocated disk blocks
+    #[arg(long)]
+    apparent: bool,
+    /// Don't cross into other mounted file systems
+    #[arg(short = 'x', long)]
+    one_file_system: bool,
+}
+
+/// Which backups `backup prune` keeps; a backup survives if any rule keeps it
+#[derive(Args, Clone, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetentionArgs {
+    /// Keep the N most recent backups
+    #[arg(long, value_name = "N")]
+    keep_last: Option<usize>,
+    /// Keep the newest backup of each of the last N days
+    #[arg(long, value_name = "N")]
+    keep_daily: Option<usize>,
+    /// Keep the newest backup of each of the last N ISO weeks
+    #[arg(long, value_name = "N")]
+    keep_weekly: Option<usize>,
+    /// Keep the newest backup of each of the last N months
+    #[arg(long, value_name = "N")]
+    keep_monthly: Option<usize>,
+    /// Then drop the oldest backups until the total fits (e.g. 20G, 500M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    #[serde(deserialize_with = "deserialize_size")]
+    max_total_size: Option<u64>,
+}
+
+/// Archive options shared by `zip` and `backup`
+#[derive(Args, Clone, Default)]
+struct ZipArgs {
+    /// Deflate level 0-9 (default: 6)
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(0..=9))]
+    level: Option<i32>,
+    /// Store already-compressed files (jpg, mp4, zip, …) without recompressing
+    #[arg(long, overrides_with = "no_store")]
+    store: bool,
+    /// Recompress everything, even if the config sets `store`
+    #[arg(long, overrides_with = "store")]
+    no_store: bool,
+    /// Skip paths matching a glob (gitignore syntax, repeatable)
+    #[arg(short = 'x', long = "exclude")]
+    exclude: Vec<String>,
+    /// Skip files ignored by .gitignore, plus the .git directory
+    #[arg(short, long, overrides_with = "no_gitignore")]
+    gitignore: bool,
+    /// Include ignored files, even if the config sets `gitignore`
+    #[arg(long, overrides_with = "gitignore")]
+    no_gitignore: bool,
+    /// Reproducible output: fixed timestamps, so identical trees give identical zips
+    #[arg(long)]
+    reproducible: bool,
+    /// Encrypt entries with AES-256 (password from --password-file, $VASU_ZIP_PASSWORD or a prompt)
+    #[arg(short, long)]
+    encrypt: bool,
+    /// Read the archive password from a file instead of prompting
+    #[arg(long, value_name = "FILE")]
+    password_file: Option<PathBuf>,
+}
+
+impl ZipArgs {
+    /// Fill in whatever the command line left unset from the `[zip]` config section
+    fn with_config(mut self) -> Self {
+        let cfg = &config().zip;
+        self.level = self.level.or(cfg.level);
+        self.store = !self.no_store && (self.store || cfg.store);
+        self.gitignore = !self.no_gitignore && (self.gitignore || cfg.gitignore);
+        self.exclude.splice(0..0, cfg.exclude.iter().cloned());
+        self
+    }
 }
 
 // ─────────────────────────────────────────────────────────────
@@ -198,24 +476,59 @@ fn main() {
     let cli = Cli::parse();
     match cli.command {
         None                   => show_banner(),
-        Some(Commands::Del   { keep, yes })              => cmd_del(keep, yes),
+        Some(Commands::Del   { keep, yes, case_sensitive }) => cmd_del(keep, yes, case_sensitive),
         Some(Commands::Cp    { src, dst, overwrite })    => cmd_cp(&src, &dst, overwrite),
-        Some(Commands::Cb    { targets, no_header })     => cmd_cb(targets, no_header),
-        Some(Commands::Tree  { directory, depth, all })  => cmd_tree(&directory, depth, all),
-        Some(Commands::Find  { pattern, directory, ftype }) => cmd_find(&pattern, &directory, &ftype),
-        Some(Commands::Size  { directory, top })         => cmd_size(&directory, top),
-        Some(Commands::Clean { directory, yes })         => cmd_clean(&directory, yes),
-        Some(Commands::Zip   { source, output })         => cmd_zip(&source, output),
-        Some(Commands::Unzip { archive, destination })   => cmd_unzip(&archive, &destination),
-        Some(Commands::Rename { pattern, replacement, directory, dry_run }) =>
-            cmd_rename(&pattern, &replacement, &directory, dry_run),
+        Some(Commands::Cb    { targets, no_header, case_sensitive }) =>
+            cmd_cb(targets, no_header || config().cb.no_header, case_sensitive),
+        Some(Commands::Tree  { directory, depth, all })  =>
+            cmd_tree(&directory, depth.unwrap_or(config().tree.depth), all || config().tree.all),
+        Some(Commands::Find  { pattern, directory, ftype, case_sensitive, substring, filters, actions }) =>
+            cmd_find(&pattern, &directory, ftype.as_deref().unwrap_or(&config().find.r#type), case_sensitive, substring, &filters, &actions),
+        Some(Commands::Grep  { pattern, paths, opts })   => cmd_grep(pattern.as_deref(), &paths, &opts),
+        Some(Commands::Replace { pattern, replacement, paths, opts }) =>
+            cmd_replace(&pattern, &replacement, &paths, &opts),
+        Some(Commands::Size  { directory, interactive: true, opts, .. }) => cmd_size_browse(&directory, opts),
+        Some(Commands::Size  { directory, top, files, dirs, min_size, ext, opts, .. }) if files || dirs =>
+            cmd_size_top(&directory, top.unwrap_or(config().size.top), dirs, min_size.unwrap_or(0), &ext, opts),
+        Some(Commands::Size  { directory, history: SizeHistoryArgs { history: true, .. }, .. }) =>
+            cmd_size_history(&directory),
+        Some(Commands::Size  { directory, top, depth, by, opts, history, .. }) =>
+            cmd_size(&directory, top.unwrap_or(config().size.top), depth, by.as_deref(), opts, &history),
+        Some(Commands::Clean { directory, yes, preset, list_rules, older_than, scan }) =>
+            cmd_clean(&directory, yes, &preset, list_rules, older_than, scan),
+        Some(Commands::Zip   { source, output, opts })   => { cmd_zip(&source, output, &opts.with_config()); }
+        Some(Commands::Unzip { archive, destination, password_file }) =>
+            cmd_unzip(&archive, &destination, password_file.as_deref()),
+        Some(Commands::LsArchive  { archive, depth })    => cmd_ls_archive(&archive, depth),
+        Some(Commands::CatArchive { archive, entry })    => cmd_cat_archive(&archive, &entry),
+        Some(Commands::Rename { pattern, replacement, path, directory, opts }) => {
+            let (pattern, replacement, directory) = rename_positionals(pattern, replacement, path.or(directory), &opts);
+            cmd_rename(pattern.as_deref(), replacement.as_deref(), &directory, &opts)
+        }
         Some(Commands::Count { directory, ext })         => cmd_count(&directory, ext),
         Some(Commands::Hash  { file })                   => cmd_hash(&file),
-        Some(Commands::Backup { source, dest })          => cmd_backup(&source, &dest),
+        Some(Commands::Backup { action: Some(BackupAction::List { repo }), .. }) => cmd_backup_list(&repo),
+        Some(Commands::Backup { action: Some(BackupAction::Restore { id, path, repo, to, overwrite }), .. }) =>
+            cmd_backup_restore(&repo, &id, path.as_deref(), to, overwrite),
+        Some(Commands::Backup { action: Some(BackupAction::Verify { target, source, id, password_file }), .. }) =>
+            cmd_backup_verify(&target, source.as_deref(), id.as_deref(), password_file.as_deref()),
+        Some(Commands::Backup { action: Some(BackupAction::Prune { dir, policy, dry_run, yes }), .. }) =>
+            cmd_backup_prune(&dir, &policy, dry_run, yes),
+        Some(Commands::Backup { action: None, profile: Some(name), opts, .. }) =>
+            cmd_backup_profiles(Some(&name), &opts),
+        Some(Commands::Backup { action: None, all_profiles: true, opts, .. }) =>
+            cmd_backup_profiles(None, &opts),
+        Some(Commands::Backup { action: None, source, dest, incremental, opts, .. }) => {
+            let dest = dest
+                .or_else(|| config().backup.dest.clone())
+                .unwrap_or_else(|| PathBuf::from("."));
+            cmd_backup(&source, &dest, &opts.with_config(), incremental)
+        }
         Some(Commands::Env   { filter })                 => cmd_env(&filter),
-        Some(Commands::Http  { port, directory })        => cmd_http(port, &directory),
+        Some(Commands::Http  { port, directory })        => cmd_http(port.unwrap_or(config().http.port), &directory),
         Some(Commands::Diff  { dir_a, dir_b })           => cmd_diff(&dir_a, &dir_b),
         Some(Commands::Dupe  { directory })              => cmd_dupe(&directory),
+        Some(Commands::Config { action })                => cmd_config(action),
     }
 }
 
@@ -244,19 +557,25 @@ fn show_banner() {
         ("vasu cb *",          "Copy ALL file contents to clipboard (recursive)"),
         ("vasu cb f1 f2 dir/", "Copy specific files/globs to clipboard"),
         ("vasu tree",          "Pretty directory tree"),
-        ("vasu find '*.rs'",   "Find files by name pattern"),
+        ("vasu find '*.rs'",   "Find files by glob, size, age, content"),
+        ("vasu grep re src/",  "Search file contents (gitignore-aware)"),
+        ("vasu replace a b",   "Search & replace with diff preview"),
         ("vasu size",          "Disk usage per item, sorted"),
         ("vasu clean",         "Remove build artifacts & junk"),
         ("vasu zip src/",      "Zip a file/folder"),
         ("vasu unzip f.zip",   "Unzip an archive"),
+        ("vasu ls-archive f.zip", "List archive contents as a tree"),
+        ("vasu cat-archive f.zip p", "Print one archive entry to stdout"),
         ("vasu rename p r",    "Bulk rename files"),
         ("vasu count",         "Count files & lines of code"),
         ("vasu hash file",     "Show MD5/SHA256"),
         ("vasu backup",        "Timestamped zip backup"),
+        ("vasu backup -i -d repo/", "Incremental, deduplicated backup"),
         ("vasu env [filter]",  "Print env vars"),
         ("vasu http [port]",   "Quick HTTP file server"),
         ("vasu diff a/ b/",    "Compare two directories"),
         ("vasu dupe",          "Find duplicate files"),
+        ("vasu config list",   "Show / edit configuration"),
     ];
 
     println!("  {:<32} {}", "COMMAND".bold().underline(), "DESCRIPTION".bold().underline());
@@ -269,14 +588,28 @@ fn show_banner() {
 // ─────────────────────────────────────────────────────────────
 //  DEL
 // ─────────────────────────────────────────────────────────────
-fn cmd_del(keep: Vec<String>, yes: bool) {
+fn cmd_del(keep: Vec<String>, yes: bool, case_sensitive: bool) {
     let cwd = std::env::current_dir().unwrap();
-    let keep_set: std::collections::HashSet<_> = keep.iter().collect();
+    let keep_names: Vec<&str> = keep.iter().map(|k| k.trim_end_matches('/')).collect();
+    // a name that exists as typed is always kept as-is, even if it looks like a glob
+    let keep_set: Vec<glob::Pattern> = keep_names.iter()
+        .filter(|k| glob::is_glob(k) && !cwd.join(k).exists())
+        .map(|k| glob::Pattern::new(k, case_sensitive))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{} {}", "✗".red(), e);
+            std::process::exit(1);
+        });
 
     let to_delete: Vec<_> = std::fs::read_dir(&cwd)
         .unwrap()
         .filter_map(|e| e.ok())
-        .filter(|e| !keep_set.contains(&e.file_name().to_string_lossy().to_string()))
+        .filter(|e| {
+            let name = PathBuf::from(e.file_name());
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            !keep_names.iter().any(|k| name == Path::new(k))
+                && !keep_set.iter().any(|k| k.matches(&name, is_dir))
+        })
         .collect();
 
     if to_delete.is_empty() {
@@ -356,7 +689,7 @@ fn cmd_cp(src: &Path, dst: &Path, overwrite: bool) {
 // ─────────────────────────────────────────────────────────────
 //  CB  — clipboard
 // ─────────────────────────────────────────────────────────────
-fn cmd_cb(targets: Vec<String>, no_header: bool) {
+fn cmd_cb(targets: Vec<String>, no_header: bool, case_sensitive: bool) {
     let effective: Vec<String> = if targets.is_empty() {
         vec![".".to_string()]
     } else {
@@ -372,7 +705,11 @@ fn cmd_cb(targets: Vec<String>, no_header: bool) {
         } else if p.is_file() {
             files.push(p);
         } else {
-            for entry in glob::glob(t).into_iter().flatten().flatten() {
+            let matches = glob::glob(t, case_sensitive).unwrap_or_else(|e| {
+                eprintln!("{} {}", "✗".red(), e);
+                Vec::new()
+            });
+            for entry in matches {
                 if entry.is_file() {
                     files.push(entry);
                 } else if entry.is_dir() {
@@ -518,504 +855,4195 @@ fn print_tree(dir: &Path, prefix: &str, max_depth: usize, current: usize, show_a
 // ─────────────────────────────────────────────────────────────
 //  FIND
 // ─────────────────────────────────────────────────────────────
-fn cmd_find(pattern: &str, directory: &Path, ftype: &str) {
+fn cmd_find(
+    pattern: &str,
+    directory: &Path,
+    ftype: &str,
+    case_sensitive: bool,
+    substring: bool,
+    filters: &FindFilters,
+    actions: &FindActions,
+) {
+    use std::io::{IsTerminal, Write};
     use walkdir::WalkDir;
     let mut results: Vec<PathBuf> = Vec::new();
 
-    for entry in WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-        let name = entry.file_name().to_string_lossy().to_string();
-        if !glob_match(pattern, &name) { continue; }
+    // a plain name matches exactly, like any other glob; --substring finds names containing it
+    let glob = if substring { format!("*{}*", glob::escape(pattern)) } else { pattern.to_string() };
+    let glob = glob::Pattern::new(&glob, case_sensitive).unwrap_or_else(|e| {
+        eprintln!("{} {}", "✗".red(), e);
+        std::process::exit(1);
+    });
+    let pred = FindPred::from_filters(filters, case_sensitive).unwrap_or_else(|e| {
+        eprintln!("{} {}", "✗".red(), e);
+        std::process::exit(1);
+    });
+
+    let walker = WalkDir::new(directory).min_depth(1).max_depth(filters.max_depth.unwrap_or(usize::MAX));
+    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        let rel = entry.path().strip_prefix(directory).unwrap_or(entry.path());
+        if !glob.matches(rel, entry.file_type().is_dir()) { continue; }
         match ftype {
             "f" if !entry.file_type().is_file() => continue,
             "d" if !entry.file_type().is_dir()  => continue,
             _ => {}
         }
+        if pred.as_ref().is_some_and(|p| !p.eval(&entry)) { continue; }
         results.push(entry.into_path());
     }
 
+    let plain = actions.plain || actions.print0 || !std::io::stdout().is_terminal();
     if results.is_empty() {
-        println!("{}", format!("No matches for '{pattern}'").yellow());
+        if !plain { println!("{}", format!("No matches for '{pattern}'").yellow()); }
+        return;
+    }
+
+    if let Some(cmd) = actions.exec.as_ref().or(actions.exec_batch.as_ref()) {
+        if find_exec(cmd, &results, actions.exec_batch.is_some()) > 0 { std::process::exit(1); }
+        return;
+    }
+    if actions.delete {
+        if find_delete(&results, actions.yes) > 0 { std::process::exit(1); }
+        return;
+    }
+    if plain {
+        let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+        let sep = if actions.print0 { b'\0' } else { b'\n' };
+        for r in &results {
+            let _ = out.write_all(r.as_os_str().as_encoded_bytes()).and_then(|_| out.write_all(&[sep]));
+        }
         return;
     }
 
     println!("\n{}\n", format!("Found {} match(es):", results.len()).green().bold());
-    let base = directory.canonicalize().unwrap_or(directory.to_path_buf());
     for r in &results {
-        let rel = r.strip_prefix(&base).unwrap_or(r);
+        let rel = r.strip_prefix(directory).unwrap_or(r);
         let icon = if r.is_dir() { "📁" } else { "📄" };
         println!("  {icon}  {}", rel.display().to_string().cyan());
     }
     println!();
 }
 
-// Simple glob: supports * wildcard
-fn glob_match(pattern: &str, text: &str) -> bool {
-    if pattern == "*" { return true; }
-    let lower_p = pattern.to_lowercase();
-    let lower_t = text.to_lowercase();
-    if !lower_p.contains('*') {
-        return lower_t.contains(&lower_p);
-    }
-    let parts: Vec<&str> = lower_p.split('*').collect();
-    let mut pos = 0usize;
-    for (i, part) in parts.iter().enumerate() {
-        if part.is_empty() { continue; }
-        if i == 0 {
-            if !lower_t.starts_with(part) { return false; }
-            pos = part.len();
-        } else {
-            match lower_t[pos..].find(part) {
-                Some(p) => pos += p + part.len(),
-                None    => return false,
-            }
-        }
-    }
-    if lower_p.ends_with('*') { true } else { pos == lower_t.len() || lower_t.ends_with(parts.last().unwrap_or(&"")) }
+/// Extra `find` tests. The flags are ANDed together; `--where` combines the same tests freely.
+#[derive(Args, Clone, Default)]
+struct FindFilters {
+    /// Size: +10M (larger than), -1K (smaller than), 512 (exactly)
+    #[arg(long, allow_hyphen_values = true)]
+    size: Option<String>,
+    /// Modified within this age or after a date (2d, 12h, 2026-01-31)
+    #[arg(long)]
+    newer: Option<String>,
+    /// Modified longer ago than this age or before a date (1y, 2025-06-01)
+    #[arg(long)]
+    older: Option<String>,
+    /// Permission bits: 644 (exactly), -111 (all of), /022 (any of)
+    #[arg(long, allow_hyphen_values = true)]
+    perm: Option<String>,
+    /// Empty files and empty directories only
+    #[arg(long)]
+    empty: bool,
+    /// Don't descend more than N levels below the root
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Name must match this regular expression
+    #[arg(long)]
+    regex: Option<String>,
+    /// File content must contain this text
+    #[arg(long)]
+    contains: Option<String>,
+    /// Combine tests with and/or/not and parentheses, e.g. "size +10M and (newer 2d or not empty)"
+    #[arg(short = 'w', long = "where", value_name = "EXPR")]
+    expr: Option<String>,
 }
 
-// ─────────────────────────────────────────────────────────────
-//  SIZE
-// ─────────────────────────────────────────────────────────────
-fn cmd_size(directory: &Path, top: usize) {
-    let mut entries: Vec<(u64, PathBuf)> = std::fs::read_dir(directory)
-        .unwrap()
-        .filter_map(|e| e.ok())
-        .map(|e| {
-            let p = e.path();
-            let size = dir_size(&p);
-            (size, p)
+enum FindPred {
+    Size(std::cmp::Ordering, u64),
+    Newer(std::time::SystemTime),
+    Older(std::time::SystemTime),
+    /// bits, and how to compare: '=' exactly, '-' all set, '/' any set
+    Perm(char, u32),
+    Empty,
+    Regex(regex::Regex),
+    Contains(regex::bytes::Regex),
+    Type(bool),
+    Name(glob::Pattern),
+    Not(Box<FindPred>),
+    And(Vec<FindPred>),
+    Or(Vec<FindPred>),
+}
+
+impl FindPred {
+    /// Build a single test from its name and argument (shared by the flags and `--where`)
+    fn test(name: &str, arg: &str, case_sensitive: bool) -> Result<FindPred, String> {
+        Ok(match name {
+            "size" => {
+                let (ord, n) = match arg.split_at(arg.find(|c: char| c != '+' && c != '-').unwrap_or(0)) {
+                    ("+", n) => (std::cmp::Ordering::Greater, n),
+                    ("-", n) => (std::cmp::Ordering::Less, n),
+                    ("", n) => (std::cmp::Ordering::Equal, n),
+                    _ => return Err(format!("invalid size '{arg}' (try +10M, -1K)")),
+                };
+                FindPred::Size(ord, parse_size(n)?)
+            }
+            "newer" => FindPred::Newer(parse_when(arg)?),
+            "older" => FindPred::Older(parse_when(arg)?),
+            "perm" => {
+                let (how, bits) = match arg.chars().next() {
+                    Some(c @ ('-' | '/')) => (c, &arg[1..]),
+                    _ => ('=', arg),
+                };
+                let bits = u32::from_str_radix(bits, 8).map_err(|_| format!("invalid mode '{arg}' (try 644, -111, /022)"))?;
+                FindPred::Perm(how, bits)
+            }
+            "regex" => FindPred::Regex(regex::RegexBuilder::new(arg)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| format!("invalid regex: {e}"))?),
+            "contains" => FindPred::Contains(regex::bytes::RegexBuilder::new(&regex::escape(arg))
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| e.to_string())?),
+            "type" => match arg {
+                "f" => FindPred::Type(false),
+                "d" => FindPred::Type(true),
+                _ => return Err(format!("invalid type '{arg}' (f or d)")),
+            },
+            "name" => FindPred::Name(glob::Pattern::new(arg, case_sensitive)?),
+            _ => return Err(format!("unknown test '{name}'")),
         })
-        .collect();
+    }
 
-    entries.sort_by(|a, b| b.0.cmp(&a.0));
+    fn from_filters(f: &FindFilters, case_sensitive: bool) -> Result<Option<FindPred>, String> {
+        let mut all = Vec::new();
+        for (name, arg) in [("size", &f.size), ("newer", &f.newer), ("older", &f.older), ("perm", &f.perm),
+                            ("regex", &f.regex), ("contains", &f.contains)] {
+            if let Some(arg) = arg {
+                all.push(FindPred::test(name, arg, case_sensitive)?);
+            }
+        }
+        if f.empty { all.push(FindPred::Empty); }
+        if let Some(expr) = &f.expr {
+            all.push(FindExpr::parse(expr, case_sensitive)?);
+        }
+        Ok((!all.is_empty()).then_some(FindPred::And(all)))
+    }
 
-    println!("\n  {:<12} {}", "SIZE".bold().underline(), "ITEM".bold().underline());
-    for (size, path) in entries.iter().take(top) {
-        let name = path.file_name().unwrap_or_default().to_string_lossy();
-        let icon = if path.is_dir() { "📁" } else { "📄" };
-        println!("  {:<12} {icon}  {}", human_size(*size).yellow(), name.white());
+    fn eval(&self, entry: &walkdir::DirEntry) -> bool {
+        let meta = || entry.metadata().ok();
+        match self {
+            FindPred::Size(ord, n) => meta().is_some_and(|m| m.is_file() && m.len().cmp(n) == *ord),
+            FindPred::Newer(t) => meta().and_then(|m| m.modified().ok()).is_some_and(|m| m > *t),
+            FindPred::Older(t) => meta().and_then(|m| m.modified().ok()).is_some_and(|m| m < *t),
+            FindPred::Perm(how, bits) => meta().is_some_and(|m| {
+                let mode = file_mode(&m);
+                match how {
+                    '-' => mode & bits == *bits,
+                    '/' => mode & bits != 0,
+                    _ => mode == *bits,
+                }
+            }),
+            FindPred::Empty => if entry.file_type().is_dir() {
+                std::fs::read_dir(entry.path()).is_ok_and(|mut rd| rd.next().is_none())
+            } else {
+                meta().is_some_and(|m| m.is_file() && m.len() == 0)
+            },
+            FindPred::Regex(re) => re.is_match(&entry.file_name().to_string_lossy()),
+            FindPred::Contains(re) => entry.file_type().is_file()
+                && std::fs::read(entry.path()).is_ok_and(|data| re.is_match(&data)),
+            FindPred::Type(dir) => entry.file_type().is_dir() == *dir,
+            FindPred::Name(g) => g.matches(Path::new(entry.file_name()), entry.file_type().is_dir()),
+            FindPred::Not(p) => !p.eval(entry),
+            FindPred::And(ps) => ps.iter().all(|p| p.eval(entry)),
+            FindPred::Or(ps) => ps.iter().any(|p| p.eval(entry)),
+        }
     }
-    println!();
 }
 
-fn dir_size(path: &Path) -> u64 {
-    if path.is_file() {
-        return path.metadata().map(|m| m.len()).unwrap_or(0);
+/// Permission bits (rwx for user/group/other plus setuid/setgid/sticky)
+#[cfg(unix)]
+fn file_mode(meta: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+#[cfg(not(unix))]
+fn file_mode(meta: &std::fs::Metadata) -> u32 {
+    if meta.permissions().readonly() { 0o444 } else { 0o644 }
+}
+
+/// Parse "2d"-style ages (counted back from now) or YYYY-MM-DD dates (local midnight)
+fn parse_when(s: &str) -> Result<std::time::SystemTime, String> {
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+            .map(std::time::SystemTime::from)
+            .ok_or_else(|| format!("invalid date '{s}'"));
     }
-    walkdir::WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
-        .sum()
+    let age = parse_age(s).map_err(|_| format!("invalid time '{s}' (try 2d, 12h, 1y or 2026-01-31)"))?;
+    Ok(std::time::SystemTime::now() - age)
 }
 
-// ─────────────────────────────────────────────────────────────
-//  CLEAN
-// ─────────────────────────────────────────────────────────────
-const CLEAN_NAMES: &[&str] = &[
-    "__pycache__", ".pytest_cache", ".mypy_cache", ".ruff_cache",
-    "target", ".DS_Store", "Thumbs.db", ".eggs",
-];
-const CLEAN_EXTS: &[&str] = &[".pyc", ".pyo", ".class", ".o", ".obj", ".log"];
+/// Recursive-descent parser for `--where`:
+/// `expr := and ("or" and)*`, `and := unary ("and"? unary)*`, `unary := "not" unary | "(" expr ")" | test`
+struct FindExpr {
+    tokens: Vec<String>,
+    pos: usize,
+    case_sensitive: bool,
+}
 
-fn cmd_clean(directory: &Path, yes: bool) {
-    use walkdir::WalkDir;
-    let mut found: Vec<PathBuf> = Vec::new();
+impl FindExpr {
+    fn parse(input: &str, case_sensitive: bool) -> Result<FindPred, String> {
+        let mut p = FindExpr { tokens: find_expr_tokens(input)?, pos: 0, case_sensitive };
+        let pred = p.or()?;
+        match p.tokens.get(p.pos) {
+            None => Ok(pred),
+            Some(t) => Err(format!("unexpected '{t}' in --where")),
+        }
+    }
 
-    for entry in WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-        let name = entry.file_name().to_string_lossy().to_string();
-        let is_junk = CLEAN_NAMES.contains(&name.as_str())
-            || CLEAN_EXTS.iter().any(|ext| name.ends_with(ext));
-        if is_junk { found.push(entry.into_path()); }
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
     }
 
-    // dedupe: remove paths that are children of already-found dirs
-    found.dedup_by(|a, b| a.starts_with(b.as_path()));
+    fn next(&mut self) -> Option<String> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
 
-    if found.is_empty() {
-        println!("{}", "Nothing to clean — already spotless! ✨".green());
-        return;
+    fn or(&mut self) -> Result<FindPred, String> {
+        let mut any = vec![self.and()?];
+        while matches!(self.peek(), Some("or" | "||" | "-o")) {
+            self.pos += 1;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 { any.remove(0) } else { FindPred::Or(any) })
     }
 
-    println!("\n{} {} item(s):", "Will remove".yellow().bold(), found.len());
-    let base = directory.canonicalize().unwrap_or(directory.to_path_buf());
-    for f in found.iter().take(15) {
-        let rel = f.strip_prefix(&base).unwrap_or(f);
-        println!("  {}", rel.display().to_string().red().dimmed());
+    fn and(&mut self) -> Result<FindPred, String> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some("and" | "&&" | "-a") => { self.pos += 1; }
+                None | Some(")" | "or" | "||" | "-o") => break,
+                _ => {}
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 { all.remove(0) } else { FindPred::And(all) })
     }
-    if found.len() > 15 { println!("  … and {} more", found.len() - 15); }
 
-    if !yes && !confirm("\nProceed?") { return; }
+    fn unary(&mut self) -> Result<FindPred, String> {
+        match self.next().as_deref() {
+            Some("not" | "!") => Ok(FindPred::Not(Box::new(self.unary()?))),
+            Some("(") => {
+                let inner = self.or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(inner),
+                    _ => Err("missing ')' in --where".into()),
+                }
+            }
+            Some("empty") => Ok(FindPred::Empty),
+            Some(name) => {
+                let arg = self.next().ok_or_else(|| format!("'{name}' needs a value in --where"))?;
+                FindPred::test(name, &arg, self.case_sensitive)
+            }
+            None => Err("--where ended early".into()),
+        }
+    }
+}
 
-    let mut removed = 0usize;
-    for f in &found {
-        let ok = if f.is_dir() {
-            std::fs::remove_dir_all(f).is_ok()
+/// Split on whitespace and parentheses; '…' and "…" keep spaces together
+fn find_expr_tokens(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let word: String = chars.by_ref().take_while(|&x| x != c).collect();
+            tokens.push(word);
         } else {
-            std::fs::remove_file(f).is_ok()
-        };
-        if ok { removed += 1; }
+            let mut word = String::new();
+            while let Some(&x) = chars.peek() {
+                if x.is_whitespace() || x == '(' || x == ')' { break; }
+                word.push(x);
+                chars.next();
+            }
+            tokens.push(word);
+        }
+    }
+    if tokens.is_empty() {
+        return Err("--where is empty".into());
     }
-    println!("{} Cleaned {} item(s).", "✓".green().bold(), removed);
+    Ok(tokens)
 }
 
-// ─────────────────────────────────────────────────────────────
-//  ZIP
-// ─────────────────────────────────────────────────────────────
-fn cmd_zip(source: &Path, output: Option<PathBuf>) {
-    use std::io::Write;
-
-    let out = output.unwrap_or_else(|| {
-        PathBuf::from(format!("{}.zip", source.file_name().unwrap().to_string_lossy()))
-    });
+/// What `find` does with its matches instead of listing them
+#[derive(Args, Clone, Default)]
+struct FindActions {
+    /// Run a command per match; {} is the path (appended if absent). End with \; or put it last
+    #[arg(short = 'x', long, num_args = 1.., allow_hyphen_values = true, value_terminator = ";",
+          value_name = "CMD", conflicts_with_all = ["exec_batch", "delete"])]
+    exec: Option<Vec<String>>,
+    /// Run a command once with every match in place of {}. End with + or put it last
+    #[arg(short = 'X', long, num_args = 1.., allow_hyphen_values = true, value_terminator = "+",
+          value_name = "CMD", conflicts_with = "delete")]
+    exec_batch: Option<Vec<String>>,
+    /// Delete every match (asks first)
+    #[arg(long)]
+    delete: bool,
+    /// Skip the --delete confirmation
+    #[arg(short, long, requires = "delete")]
+    yes: bool,
+    /// Separate paths with NUL instead of newlines (for xargs -0)
+    #[arg(short = '0', long, conflicts_with_all = ["exec", "exec_batch", "delete"])]
+    print0: bool,
+    /// One path per line, no icons or colour (automatic when piped)
+    #[arg(long)]
+    plain: bool,
+}
 
-    let file = std::fs::File::create(&out).expect("Cannot create zip file");
-    let mut zip = zip::ZipWriter::new(file);
-    let opts = zip::write::FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated);
+/// Substitute {} in a command template; without one the paths go at the end
+fn fill_command(template: &[String], paths: &[PathBuf]) -> Vec<std::ffi::OsString> {
+    let mut args = Vec::new();
+    let mut used = false;
+    for arg in template {
+        if arg == "{}" {
+            args.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+            used = true;
+        } else if arg.contains("{}") && paths.len() == 1 {
+            args.push(arg.replace("{}", &paths[0].to_string_lossy()).into());
+            used = true;
+        } else {
+            args.push(arg.into());
+        }
+    }
+    if !used {
+        args.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+    }
+    args
+}
 
-    let mut count = 0usize;
-    if source.is_dir() {
-        for entry in walkdir::WalkDir::new(source).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            let rel = path.strip_prefix(source.parent().unwrap_or(Path::new("."))).unwrap();
-            if path.is_file() {
-                zip.start_file(rel.to_string_lossy(), opts).unwrap();
-                let data = std::fs::read(path).unwrap();
-                zip.write_all(&data).unwrap();
-                count += 1;
-            } else if path.is_dir() {
-                zip.add_directory(rel.to_string_lossy(), opts).unwrap();
+/// Run `template` per path (or once for all of them); returns how many runs failed
+fn find_exec(template: &[String], paths: &[PathBuf], batch: bool) -> usize {
+    let runs: Vec<&[PathBuf]> = if batch {
+        // stay well below the OS argument limit
+        paths.chunks(4096).collect()
+    } else {
+        paths.chunks(1).collect()
+    };
+    let mut failed = 0;
+    for chunk in runs {
+        let args = fill_command(template, chunk);
+        let status = std::process::Command::new(&args[0]).args(&args[1..]).status();
+        match status {
+            Ok(s) if s.success() => {}
+            Ok(s) => {
+                failed += 1;
+                eprintln!("{} {} exited with {}", "✗".red(), template[0], s.code().map_or("a signal".into(), |c| c.to_string()));
+            }
+            Err(e) => {
+                eprintln!("{} {}: {}", "✗".red(), template[0], e);
+                return failed + 1;
             }
         }
-    } else {
-        zip.start_file(source.file_name().unwrap().to_string_lossy(), opts).unwrap();
-        let data = std::fs::read(source).unwrap();
-        zip.write_all(&data).unwrap();
-        count = 1;
     }
-    zip.finish().unwrap();
-
-    let size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
-    println!("{} Zipped {} file(s) → {}  ({})",
-        "✓".green().bold(), count, out.display().to_string().cyan(),
-        human_size(size).yellow());
+    failed
 }
 
-// ─────────────────────────────────────────────────────────────
-//  UNZIP
-// ─────────────────────────────────────────────────────────────
-fn cmd_unzip(archive: &Path, destination: &Path) {
-    let file = std::fs::File::open(archive).expect("Cannot open archive");
-    let mut zip = zip::ZipArchive::new(file).expect("Not a valid zip");
-    std::fs::create_dir_all(destination).unwrap();
-    let total = zip.len();
-    zip.extract(destination).expect("Extraction failed");
-    println!("{} Extracted {} files → {}",
-        "✓".green().bold(), total, destination.display().to_string().cyan());
-}
+fn find_delete(paths: &[PathBuf], yes: bool) -> usize {
+    println!("\n{} {} match(es):", "Will DELETE".red().bold(), paths.len());
+    for p in paths.iter().take(20) {
+        let icon = if p.is_dir() { "📁" } else { "📄" };
+        println!("  {icon}  {}", p.display().to_string().red());
+    }
+    if paths.len() > 20 { println!("  … and {} more", paths.len() - 20); }
+    if !yes && !confirm("\nProceed?") { return 0; }
 
-// ─────────────────────────────────────────────────────────────
-//  RENAME
-// ─────────────────────────────────────────────────────────────
-fn cmd_rename(pattern: &str, replacement: &str, directory: &Path, dry_run: bool) {
-    let mut count = 0usize;
-    for entry in walkdir::WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        if !path.is_file() { continue; }
-        let name = path.file_name().unwrap().to_string_lossy().to_string();
-        if !name.contains(pattern) { continue; }
-
-        let new_name = name.replace(pattern, replacement);
-        let new_path = path.with_file_name(&new_name);
-        let tag = if dry_run { "(dry-run)".dimmed().to_string() } else { String::new() };
-        println!("  {}  →  {}  {}", name.yellow(), new_name.green(), tag);
-        if !dry_run {
-            std::fs::rename(path, new_path).ok();
+    // deepest first, and nothing inside a folder that's already gone
+    let mut order: Vec<&PathBuf> = paths.iter().collect();
+    order.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+    let mut count = 0;
+    let mut failed = 0;
+    for p in order {
+        if std::fs::symlink_metadata(p).is_err() { continue; }
+        let result = if p.is_dir() && !p.is_symlink() { std::fs::remove_dir_all(p) } else { std::fs::remove_file(p) };
+        match result {
+            Ok(_) => count += 1,
+            Err(e) => {
+                failed += 1;
+                eprintln!("  {} {}: {}", "✗".red(), p.display(), e);
+            }
         }
-        count += 1;
-    }
-    if count == 0 {
-        println!("{}", format!("No files matched pattern '{pattern}'").yellow());
-    } else {
-        let verb = if dry_run { "Would rename" } else { "Renamed" };
-        println!("\n{} {verb} {count} file(s).", "✓".green().bold());
     }
+    println!("\n{} Deleted {} item(s).", "✓".green().bold(), count);
+    failed
 }
 
 // ─────────────────────────────────────────────────────────────
-//  COUNT
+//  GREP
 // ─────────────────────────────────────────────────────────────
-fn cmd_count(directory: &Path, ext_filter: Vec<String>) {
-    use std::collections::HashMap;
-    let filter: Vec<String> = ext_filter.iter().map(|e| {
-        if e.starts_with('.') { e.clone() } else { format!(".{e}") }
-    }).collect();
+/// Which files a content search visits
+#[derive(Args, Clone, Default)]
+struct WalkArgs {
+    /// Only files of this type, repeatable (rust, py, js, md… see --type-list)
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    types: Vec<String>,
+    /// Skip files of this type, repeatable
+    #[arg(short = 'T', long = "type-not", value_name = "TYPE")]
+    types_not: Vec<String>,
+    /// Search hidden files and folders too
+    #[arg(long)]
+    hidden: bool,
+    /// Ignore .gitignore / .ignore rules
+    #[arg(long)]
+    no_ignore: bool,
+}
 
-    let mut by_ext: HashMap<String, (usize, usize)> = HashMap::new();
+/// Parallel walker over `paths` that honours .gitignore, .ignore and file-type filters
+fn file_walker(paths: &[PathBuf], args: &WalkArgs) -> Result<ignore::WalkParallel, String> {
+    let mut types = ignore::types::TypesBuilder::new();
+    types.add_defaults();
+    for t in &args.types { types.select(t); }
+    for t in &args.types_not { types.negate(t); }
+    let types = types.build().map_err(|e| e.to_string())?;
 
-    for entry in walkdir::WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-        if !entry.file_type().is_file() { continue; }
-        let path = entry.path();
-        let ext = path.extension()
-            .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
-            .unwrap_or_else(|| "(no ext)".into());
+    let mut builder = ignore::WalkBuilder::new(&paths[0]);
+    for p in &paths[1..] { builder.add(p); }
+    builder
+        .hidden(!args.hidden)
+        .ignore(!args.no_ignore)
+        .git_ignore(!args.no_ignore)
+        .git_global(!args.no_ignore)
+        .git_exclude(!args.no_ignore)
+        .parents(!args.no_ignore)
+        .require_git(false)
+        .types(types)
+        .filter_entry(|e| e.file_name() != ".git");
+    Ok(builder.build_parallel())
+}
 
-        if !filter.is_empty() && !filter.contains(&ext) { continue; }
+/// Run `visit` on every file in parallel; returns what it found and how many paths couldn't be walked
+fn walk_files<T: Send>(paths: &[PathBuf], args: &WalkArgs, visit: impl Fn(&Path) -> Option<T> + Sync) -> (Vec<T>, usize) {
+    let walker = file_walker(paths, args).unwrap_or_else(|e| {
+        eprintln!("{} {}", "✗".red(), e);
+        std::process::exit(1);
+    });
+    let found = std::sync::Mutex::new(Vec::new());
+    let errors = std::sync::atomic::AtomicUsize::new(0);
+    walker.run(|| {
+        let (found, visit, errors) = (&found, &visit, &errors);
+        Box::new(move |entry| {
+            match entry {
+                Ok(e) if e.file_type().is_some_and(|t| t.is_file()) => {
+                    if let Some(hit) = visit(e.path()) {
+                        found.lock().unwrap().push(hit);
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("{} {}", "✗".red(), err);
+                    errors.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+            ignore::WalkState::Continue
+        })
+    });
+    (found.into_inner().unwrap(), errors.into_inner())
+}
 
-        let lines = std::fs::read_to_string(path)
-            .map(|s| s.lines().count())
-            .unwrap_or(0);
+/// True when the first 8 KB hold a NUL byte — the same heuristic git and grep use
+fn looks_binary(data: &[u8]) -> bool {
+    data[..data.len().min(8192)].contains(&0)
+}
 
-        let entry = by_ext.entry(ext).or_insert((0, 0));
-        entry.0 += 1;
-        entry.1 += lines;
-    }
+/// How a search pattern is interpreted (shared by grep and replace)
+#[derive(Args, Clone, Default)]
+struct MatchArgs {
+    /// Case-insensitive
+    #[arg(short, long)]
+    ignore_case: bool,
+    /// Match whole words only
+    #[arg(short, long)]
+    word: bool,
+    /// Treat the pattern as literal text, not a regex
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+}
 
-    if by_ext.is_empty() {
-        println!("{}", "No files found.".yellow());
-        return;
+impl MatchArgs {
+    fn regex(&self, pattern: &str) -> regex::bytes::Regex {
+        let mut pat = if self.fixed_strings { regex::escape(pattern) } else { pattern.to_string() };
+        if self.word {
+            pat = format!(r"\b(?:{pat})\b");
+        }
+        regex::bytes::RegexBuilder::new(&pat)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .unwrap_or_else(|e| {
+                eprintln!("{} Invalid pattern: {}", "✗".red(), e);
+                std::process::exit(2);
+            })
     }
+}
 
-    let mut rows: Vec<_> = by_ext.iter().collect();
-    rows.sort_by(|a, b| b.1.0.cmp(&a.1.0));
-
-    println!("\n  {:<14} {:<10} {}", "EXTENSION".bold(), "FILES".bold(), "LINES".bold());
-    let (mut tf, mut tl) = (0usize, 0usize);
-    for (ext, (files, lines)) in &rows {
-        println!("  {:<14} {:<10} {:>10}", ext.cyan(), files.to_string().yellow(), format!("{lines:>10}").white());
-        tf += files; tl += lines;
-    }
-    println!("  {:<14} {:<10} {:>10}", "TOTAL".bold(), tf.to_string().bold(), format!("{tl:>10}").bold());
-    println!();
+#[derive(Args, Clone, Default)]
+struct GrepOpts {
+    #[command(flatten)]
+    matching: MatchArgs,
+    /// Show lines that don't match
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+    /// Lines of context after each match
+    #[arg(short = 'A', long, value_name = "N")]
+    after: Option<usize>,
+    /// Lines of context before each match
+    #[arg(short = 'B', long, value_name = "N")]
+    before: Option<usize>,
+    /// Lines of context around each match
+    #[arg(short = 'C', long, value_name = "N")]
+    context: Option<usize>,
+    /// Only print how many lines matched per file
+    #[arg(short, long, conflicts_with = "files_with_matches")]
+    count: bool,
+    /// Only print the names of files with a match
+    #[arg(short = 'l', long)]
+    files_with_matches: bool,
+    /// Search binary files as if they were text
+    #[arg(short = 'a', long)]
+    text: bool,
+    #[command(flatten)]
+    walk: WalkArgs,
+    /// List the file types usable with -t / -T and exit
+    #[arg(long)]
+    type_list: bool,
 }
 
-// ─────────────────────────────────────────────────────────────
-//  HASH
-// ─────────────────────────────────────────────────────────────
-fn cmd_hash(file: &Path) {
-    use sha2::{Digest, Sha256};
-    if !file.is_file() {
-        eprintln!("{} Not a file: {}", "✗".red(), file.display());
-        std::process::exit(1);
+/// Search one file; returns (path, matching line count, rendered output)
+fn grep_file(path: &Path, data: &[u8], re: &regex::bytes::Regex, opts: &GrepOpts, pretty: bool) -> Option<(PathBuf, usize, String)> {
+    use std::fmt::Write;
+
+    if !opts.text && looks_binary(data) { return None; }
+    let mut lines: Vec<&[u8]> = data.split(|&b| b == b'\n').collect();
+    if data.ends_with(b"\n") { lines.pop(); }
+    let matched: Vec<usize> = (0..lines.len()).filter(|&i| re.is_match(lines[i]) != opts.invert_match).collect();
+    if matched.is_empty() { return None; }
+    if opts.count || opts.files_with_matches {
+        return Some((path.to_path_buf(), matched.len(), String::new()));
     }
-    let data = std::fs::read(file).expect("Cannot read file");
-    let md5_hash = format!("{:x}", md5::compute(&data));
-    let sha256_hash = format!("{:x}", Sha256::digest(&data));
-    let size = human_size(data.len() as u64);
 
-    println!("\n  {} {}", "File:  ".bold(), file.display().to_string().cyan());
-    println!("  {} {}", "Size:  ".bold(), size.yellow());
-    println!("  {} {}", "MD5:   ".bold(), md5_hash.green());
-    println!("  {} {}", "SHA256:".bold(), sha256_hash.green());
-    println!();
+    let before = opts.before.or(opts.context).unwrap_or(0);
+    let after = opts.after.or(opts.context).unwrap_or(0);
+    let shown_path = path.strip_prefix("./").unwrap_or(path).display().to_string();
+    let mut out = String::new();
+    let mut last: Option<usize> = None;
+    for &m in &matched {
+        let from = m.saturating_sub(before).max(last.map_or(0, |l| l + 1));
+        let to = (m + after).min(lines.len() - 1);
+        for (i, raw) in lines.iter().enumerate().take(to + 1).skip(from) {
+            if (before > 0 || after > 0) && last.is_some_and(|l| i > l + 1) {
+                out.push_str(&if pretty { "--".dimmed().to_string() } else { "--".into() });
+                out.push('\n');
+            }
+            last = Some(i);
+            let is_match = matched.binary_search(&i).is_ok();
+            let line = raw.strip_suffix(b"\r").unwrap_or(raw);
+            let sep = if is_match { ':' } else { '-' };
+            if pretty {
+                let _ = write!(out, "{}{}", (i + 1).to_string().green(), sep.to_string().dimmed());
+                let mut at = 0;
+                if is_match && !opts.invert_match {
+                    for hit in re.find_iter(line) {
+                        out.push_str(&String::from_utf8_lossy(&line[at..hit.start()]));
+                        out.push_str(&String::from_utf8_lossy(hit.as_bytes()).red().bold().to_string());
+                        at = hit.end();
+                    }
+                }
+                out.push_str(&String::from_utf8_lossy(&line[at..]));
+            } else {
+                let _ = write!(out, "{shown_path}{sep}{}{sep}{}", i + 1, String::from_utf8_lossy(line));
+            }
+            out.push('\n');
+        }
+    }
+    Some((path.to_path_buf(), matched.len(), out))
 }
 
-// ─────────────────────────────────────────────────────────────
-//  BACKUP
-// ─────────────────────────────────────────────────────────────
-fn cmd_backup(source: &Path, dest: &Path) {
-    use chrono::Local;
-    let stamp = Local::now().format("%Y%m%d_%H%M%S");
-    let name = source.file_name().unwrap_or(std::ffi::OsStr::new("backup"))
-        .to_string_lossy();
-    std::fs::create_dir_all(dest).unwrap();
-    let out = dest.join(format!("{name}_{stamp}.zip"));
-    cmd_zip(source, Some(out.clone()));
-    let size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
-    println!("{} Backup saved → {}  ({})",
-        "✓".green().bold(), out.display().to_string().cyan(), human_size(size).yellow());
-}
+fn cmd_grep(pattern: Option<&str>, paths: &[PathBuf], opts: &GrepOpts) {
+    use std::io::IsTerminal;
 
-// ─────────────────────────────────────────────────────────────
-//  ENV
-// ─────────────────────────────────────────────────────────────
-fn cmd_env(filter: &str) {
-    let lower = filter.to_lowercase();
-    let mut vars: Vec<(String, String)> = std::env::vars()
-        .filter(|(k, v)| {
-            filter.is_empty()
-                || k.to_lowercase().contains(&lower)
-                || v.to_lowercase().contains(&lower)
-        })
-        .collect();
-    vars.sort_by(|a, b| a.0.cmp(&b.0));
+    if opts.type_list {
+        let mut types = ignore::types::TypesBuilder::new();
+        types.add_defaults();
+        for def in types.definitions() {
+            println!("{}: {}", def.name().cyan(), def.globs().join(", "));
+        }
+        return;
+    }
+    let Some(pattern) = pattern else {
+        eprintln!("{} Missing pattern", "✗".red());
+        std::process::exit(2);
+    };
+    let re = opts.matching.regex(pattern);
+    let pretty = std::io::stdout().is_terminal();
 
-    println!();
-    for (k, v) in &vars {
-        println!("  {}={}", k.cyan().bold(), v.yellow());
+    // like grep and rg: 0 = matched, 1 = no match, 2 = something couldn't be read
+    let unreadable = std::sync::atomic::AtomicUsize::new(0);
+    let (mut hits, walk_errors) = walk_files(paths, &opts.walk, |path| {
+        match std::fs::read(path) {
+            Ok(data) => grep_file(path, &data, &re, opts, pretty),
+            Err(e) => {
+                eprintln!("{} {}: {}", "✗".red(), path.display(), e);
+                unreadable.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                None
+            }
+        }
+    });
+    hits.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (path, count, out) in &hits {
+        let shown = path.strip_prefix("./").unwrap_or(path).display().to_string();
+        if opts.files_with_matches {
+            println!("{}", if pretty { shown.magenta().to_string() } else { shown });
+        } else if opts.count {
+            if pretty { println!("{}:{}", shown.magenta(), count) } else { println!("{shown}:{count}") }
+        } else if pretty {
+            println!("{}\n{}", shown.magenta().bold(), out);
+        } else {
+            print!("{out}");
+        }
+    }
+    if pretty && !opts.count && !opts.files_with_matches && !hits.is_empty() {
+        let lines: usize = hits.iter().map(|h| h.1).sum();
+        println!("{}", format!("{} matching line(s) in {} file(s)", lines, hits.len()).dimmed());
+    }
+    if walk_errors + unreadable.into_inner() > 0 {
+        std::process::exit(2);
+    }
+    if hits.is_empty() {
+        std::process::exit(1);
     }
-    println!();
 }
 
 // ─────────────────────────────────────────────────────────────
-//  HTTP
+//  REPLACE
 // ─────────────────────────────────────────────────────────────
-fn cmd_http(port: u16, directory: &Path) {
-    use std::net::TcpListener;
-    use std::io::{Write, BufRead, BufReader};
-
-    let dir = directory.canonicalize().unwrap_or(directory.to_path_buf());
-    std::env::set_current_dir(&dir).unwrap();
+#[derive(Args, Clone, Default)]
+struct ReplaceOpts {
+    #[command(flatten)]
+    matching: MatchArgs,
+    /// Show the changes without writing anything
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Apply to every file without asking
+    #[arg(short, long)]
+    yes: bool,
+    #[command(flatten)]
+    walk: WalkArgs,
+}
 
-    println!("{} Serving {} at {}",
-        "⚡".yellow(),
-        dir.display().to_string().yellow(),
-        format!("http://localhost:{port}").cyan().bold(),
-    );
-    println!("{}", "Press Ctrl-C to stop\n".dimmed());
+/// Planned rewrite of one file
+struct FileEdit {
+    path: PathBuf,
+    old: Vec<u8>,
+    new: Vec<u8>,
+    replacements: usize,
+    /// changed line ranges: (old start, old end, new start, new end) byte offsets
+    hunks: Vec<(usize, usize, usize, usize)>,
+}
 
-    let listener = TcpListener::bind(format!("0.0.0.0:{port}")).expect("Failed to bind port");
+fn plan_replace(path: &Path, old: Vec<u8>, re: &regex::bytes::Regex, replacement: &[u8], literal: bool) -> Option<FileEdit> {
+    if looks_binary(&old) { return None; }
 
-    for mut stream in listener.incoming().flatten() {
-        let mut reader = BufReader::new(stream.try_clone().unwrap());
-            let mut first_line = String::new();
-            reader.read_line(&mut first_line).ok();
+    let mut new = Vec::with_capacity(old.len());
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for caps in re.captures_iter(&old) {
+        let m = caps.get(0).unwrap();
+        new.extend_from_slice(&old[last..m.start()]);
+        let start = new.len();
+        if literal { new.extend_from_slice(replacement) } else { caps.expand(replacement, &mut new) }
+        spans.push((m.start(), m.end(), start, new.len()));
+        last = m.end();
+    }
+    new.extend_from_slice(&old[last..]);
+    if new == old { return None; }
 
-            let path_str = first_line
-                .split_whitespace().nth(1).unwrap_or("/")
-                .to_string();
-            let decoded = percent_decode(&path_str);
-            let rel_path = decoded.trim_start_matches('/');
-            let full_path = if rel_path.is_empty() {
-                dir.clone()
-            } else {
-                dir.join(rel_path)
-            };
+    // widen every replacement to whole lines, merging those that share a line
+    let line_start = |i: usize| old[..i].iter().rposition(|&b| b == b'\n').map_or(0, |p| p + 1);
+    let line_end = |i: usize| old[i..].iter().position(|&b| b == b'\n').map_or(old.len(), |p| i + p + 1);
+    let mut hunks: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for &(os, oe, ns, ne) in &spans {
+        let ls = line_start(os);
+        let le = line_end(if oe > os { oe - 1 } else { os });
+        let (nls, nle) = (ns - (os - ls), ne + (le - oe));
+        match hunks.last_mut() {
+            Some(h) if ls < h.1 => { h.1 = le; h.3 = nle; }
+            _ => hunks.push((ls, le, nls, nle)),
+        }
+    }
+    Some(FileEdit { path: path.to_path_buf(), old, new, replacements: spans.len(), hunks })
+}
 
-            let response = if full_path.is_dir() {
-                let mut body = format!(
-                    "<html><head><meta charset='utf-8'></head><body><h2>📁 {}</h2><ul>",
-                    full_path.display()
-                );
-                if let Ok(entries) = std::fs::read_dir(&full_path) {
-                    let mut items: Vec<_> = entries.filter_map(|e| e.ok()).collect();
-                    items.sort_by_key(|e| e.file_name());
-                    for item in items {
-                        let n = item.file_name().to_string_lossy().to_string();
-                        let href = format!("{}/{}", path_str.trim_end_matches('/'), n);
-                        let icon = if item.path().is_dir() { "📁" } else { "📄" };
-                        body.push_str(&format!("<li>{icon} <a href='{href}'>{n}</a></li>"));
-                    }
-                }
-                body.push_str("</ul></body></html>");
-                format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}",
-                    body.len(), body)
-            } else if full_path.is_file() {
-                match std::fs::read(&full_path) {
-                    Ok(data) => {
-                        println!("  {} {}", "GET".green(), path_str);
-                        let mime = guess_mime(full_path.extension()
-                            .unwrap_or_default().to_str().unwrap_or(""));
-                        let header = format!(
-                            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
-                            mime, data.len()
-                        );
-                        stream.write_all(header.as_bytes()).ok();
-                        stream.write_all(&data).ok();
-                        continue;
-                    }
-                    Err(_) => "HTTP/1.1 500 Internal Server Error\r\n\r\n".to_string(),
-                }
-            } else {
-                "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\n404 oops".to_string()
-            };
+fn print_file_edit(edit: &FileEdit) {
+    let lines = |text: &[u8]| -> Vec<String> {
+        let text = text.strip_suffix(b"\n").unwrap_or(text);
+        text.split(|&b| b == b'\n')
+            .map(|l| String::from_utf8_lossy(l.strip_suffix(b"\r").unwrap_or(l)).to_string())
+            .collect()
+    };
+    let line_no = |text: &[u8], at: usize| text[..at].iter().filter(|&&b| b == b'\n').count() + 1;
 
-            stream.write_all(response.as_bytes()).ok();
+    let shown = edit.path.strip_prefix("./").unwrap_or(&edit.path);
+    println!("\n{}  {}", shown.display().to_string().magenta().bold(),
+        format!("({} replacement(s))", edit.replacements).dimmed());
+    for (n, &(os, oe, ns, ne)) in edit.hunks.iter().enumerate() {
+        if n > 0 && edit.hunks[n - 1].1 < os {
+            println!("  {}", "    ⋯".dimmed());
+        }
+        let (first_old, first_new) = (line_no(&edit.old, os), line_no(&edit.new, ns));
+        for (i, l) in lines(&edit.old[os..oe]).iter().enumerate() {
+            println!("  {:>5} {} {}", (first_old + i).to_string().dimmed(), "-".red(), l.red());
+        }
+        for (i, l) in lines(&edit.new[ns..ne]).iter().enumerate() {
+            println!("  {:>5} {} {}", (first_new + i).to_string().dimmed(), "+".green(), l.green());
+        }
     }
 }
 
-fn percent_decode(s: &str) -> String {
-    let mut out = String::new();
-    let mut chars = s.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == '%' {
-            let h: String = chars.by_ref().take(2).collect();
-            if let Ok(byte) = u8::from_str_radix(&h, 16) {
-                out.push(byte as char);
+fn cmd_replace(pattern: &str, replacement: &str, paths: &[PathBuf], opts: &ReplaceOpts) {
+    use std::io::Write;
+
+    let re = opts.matching.regex(pattern);
+    let literal = opts.matching.fixed_strings;
+    let unreadable = std::sync::atomic::AtomicUsize::new(0);
+    let (mut edits, walk_errors) = walk_files(paths, &opts.walk, |path| {
+        match std::fs::read(path) {
+            Ok(data) => plan_replace(path, data, &re, replacement.as_bytes(), literal),
+            Err(e) => {
+                eprintln!("{} {}: {}", "✗".red(), path.display(), e);
+                unreadable.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                None
+            }
+        }
+    });
+    let unreadable = walk_errors + unreadable.into_inner();
+    edits.sort_by(|a, b| a.path.cmp(&b.path));
+    if edits.is_empty() {
+        println!("{}", format!("No matches for '{pattern}'").yellow());
+        if unreadable > 0 { std::process::exit(1); }
+        return;
+    }
+
+    let mut apply_all = opts.yes;
+    let (mut files, mut total, mut failed) = (0usize, 0usize, unreadable);
+    for edit in &edits {
+        print_file_edit(edit);
+        if opts.dry_run { continue; }
+        if !apply_all {
+            print!("{} ", "Apply? [y]es / [n]o / [a]ll / [q]uit".yellow());
+            std::io::stdout().flush().unwrap();
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input).unwrap();
+            match input.trim().to_lowercase().as_str() {
+                "y" | "yes" => {}
+                "a" | "all" => apply_all = true,
+                "q" | "quit" => break,
+                _ => continue,
+            }
+        }
+        // the file may have been edited while we were asking; don't overwrite that
+        if std::fs::read(&edit.path).ok().as_ref() != Some(&edit.old) {
+            failed += 1;
+            eprintln!("  {} {}: changed since it was read, left as is", "✗".red(), edit.path.display());
+            continue;
+        }
+        match write_atomic(&edit.path, &edit.new) {
+            Ok(()) => { files += 1; total += edit.replacements; }
+            Err(e) => {
+                failed += 1;
+                eprintln!("  {} {}: {}", "✗".red(), edit.path.display(), e);
             }
-        } else {
-            out.push(c);
         }
     }
-    out
-}
 
-fn guess_mime(ext: &str) -> &'static str {
-    match ext {
-        "html" | "htm" => "text/html",
-        "css"          => "text/css",
-        "js"           => "application/javascript",
-        "json"         => "application/json",
-        "png"          => "image/png",
-        "jpg" | "jpeg" => "image/jpeg",
-        "gif"          => "image/gif",
-        "svg"          => "image/svg+xml",
-        "pdf"          => "application/pdf",
-        "txt" | "md"   => "text/plain",
-        _              => "application/octet-stream",
+    if opts.dry_run {
+        let total: usize = edits.iter().map(|e| e.replacements).sum();
+        println!("\n{} Would replace {} occurrence(s) in {} file(s).", "✓".green().bold(), total, edits.len());
+    } else {
+        println!("\n{} Replaced {} occurrence(s) in {} file(s).", "✓".green().bold(), total, files);
     }
+    if failed > 0 { std::process::exit(1); }
 }
 
 // ─────────────────────────────────────────────────────────────
-//  DIFF
+//  SIZE
 // ─────────────────────────────────────────────────────────────
-fn cmd_diff(dir_a: &Path, dir_b: &Path) {
-    use std::collections::HashSet;
+fn cmd_size(directory: &Path, top: usize, depth: usize, by: Option<&str>, opts: SizeOpts, history: &SizeHistoryArgs) {
+    let root = directory.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Not found: {}", "✗".red(), directory.display());
+        std::process::exit(1);
+    });
+    let nodes = scan_tree_with_progress(&root, opts);
+    let Some(total) = nodes.first() else { return };
+    if let Some(since) = &history.since {
+        print_size_since(&root, &nodes, since, depth, top, opts.apparent);
+    } else if let Some(by) = by {
+        print_size_buckets(&nodes, by, top);
+    } else {
+        println!("\n  {:<10}  {:<23}  {}", "SIZE".bold().underline(), "SHARE".bold().underline(), "ITEM".bold().underline());
+        print_size_level(&nodes, 0, "", depth.max(1), top, total.size);
+    }
+    if history.since.is_none() {
+        println!("\n  {} in {} files{}", human_size(total.size).yellow().bold(), total.files,
+            if opts.apparent { " (apparent size)" } else { "" });
+    }
+    if let Some(label) = &history.save {
+        save_size_snapshot(&root, &nodes, opts.apparent, label);
+    }
+    println!();
+}
 
-    fn all_files(root: &Path) -> HashSet<String> {
-        walkdir::WalkDir::new(root)
+/// The `n` largest items, largest first, never holding more than `n + 1` in memory
+fn top_n<T: Ord>(items: impl Iterator<Item = T>, n: usize) -> Vec<T> {
+    let mut heap = std::collections::BinaryHeap::with_capacity(n + 1);
+    for item in items {
+        heap.push(std::cmp::Reverse(item));
+        if heap.len() > n { heap.pop(); }
+    }
+    let mut top: Vec<T> = heap.into_iter().map(|r| r.0).collect();
+    top.sort_by(|a, b| b.cmp(a));
+    top
+}
+
+/// Largest individual files (streamed) or folders (from a full scan) anywhere under `directory`
+fn cmd_size_top(directory: &Path, top: usize, dirs: bool, min_size: u64, exts: &[String], opts: SizeOpts) {
+    let root = directory.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Not found: {}", "✗".red(), directory.display());
+        std::process::exit(1);
+    });
+    let exts: Vec<String> = exts.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect();
+
+    let mut considered = 0u64;
+    let largest: Vec<(u64, PathBuf)> = if dirs {
+        let nodes = scan_tree_with_progress(&root, opts);
+        let folders = nodes.iter().skip(1).filter(|n| n.is_dir);
+        considered = folders.clone().count() as u64;
+        top_n(folders.filter(|n| n.size >= min_size).map(|n| (n.size, n.path.clone())), top)
+    } else {
+        let spinner = indicatif::ProgressBar::new_spinner();
+        spinner.set_style(indicatif::ProgressStyle::with_template("{spinner:.cyan} {msg}").unwrap());
+        spinner.enable_steady_tick(std::time::Duration::from_millis(80));
+        let mut seen = std::collections::HashSet::new();
+        let files = walkdir::WalkDir::new(&root)
+            .same_file_system(opts.one_file_system)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
-            .map(|e| e.path().strip_prefix(root).unwrap().to_string_lossy().to_string())
-            .collect()
+            .filter(|e| exts.is_empty() || e.path().extension()
+                .is_some_and(|x| exts.contains(&x.to_string_lossy().to_lowercase())))
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                if !hard_link_id(&meta).is_none_or(|key| seen.insert(key)) { return None; }
+                considered += 1;
+                if considered.is_multiple_of(1000) {
+                    spinner.set_message(format!("Scanning {}… {} files", root.display(), considered));
+                }
+                let size = if opts.apparent { meta.len() } else { allocated_size(&meta) };
+                (size >= min_size).then(|| (size, e.into_path()))
+            });
+        let largest = top_n(files, top);
+        spinner.finish_and_clear();
+        largest
+    };
+
+    if largest.is_empty() {
+        println!("{}", "Nothing matched.".dimmed());
+        return;
+    }
+    println!("\n  {:<10}  {:<16}  {}", "SIZE".bold().underline(), "MODIFIED".bold().underline(), "PATH".bold().underline());
+    for (size, path) in &largest {
+        let modified = path.metadata().and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let rel = path.strip_prefix(&root).unwrap_or(path);
+        let name = if dirs { format!("{}/", rel.display()).blue().bold() } else { rel.display().to_string().white() };
+        println!("  {:>10}  {:<16}  {}", human_size(*size).yellow(), modified.dimmed(), name);
     }
+    let shown: u64 = largest.iter().map(|l| l.0).sum();
+    println!("\n  Top {} of {} {} · {} shown\n", largest.len(), considered, if dirs { "folders" } else { "files" },
+        human_size(shown).yellow().bold());
+}
 
-    let fa = all_files(dir_a);
-    let fb = all_files(dir_b);
+/// Age bucket labels, youngest first, with their upper bound in days
+const AGE_BUCKETS: &[(&str, u64)] = &[
+    ("< 1 day", 1), ("< 1 week", 7), ("< 1 month", 30), ("< 6 months", 182),
+    ("< 1 year", 365), ("< 2 years", 730), ("2+ years", u64::MAX),
+];
 
-    let only_a: Vec<_> = fa.difference(&fb).collect();
-    let only_b: Vec<_> = fb.difference(&fa).collect();
-    let changed: Vec<_> = fa.intersection(&fb)
-        .filter(|f| {
-            let a = std::fs::read(dir_a.join(f)).unwrap_or_default();
-            let b = std::fs::read(dir_b.join(f)).unwrap_or_default();
-            a != b
-        })
-        .collect();
+/// Sum file sizes into buckets (extension, age, owner or depth) and rank them by size
+fn print_size_buckets(nodes: &[SizeNode], by: &str, top: usize) {
+    let now = std::time::SystemTime::now();
+    let users = if by == "owner" { user_names() } else { Default::default() };
+    let depth = |mut i: usize| {
+        let mut d = 0;
+        while let Some(p) = nodes[i].parent { d += 1; i = p; }
+        d
+    };
 
-    if only_a.is_empty() && only_b.is_empty() && changed.is_empty() {
-        println!("{}", "✓ Directories are identical.".green().bold());
-        return;
+    let mut buckets: std::collections::HashMap<String, (u64, u64)> = std::collections::HashMap::new();
+    for (i, n) in nodes.iter().enumerate().filter(|(_, n)| !n.is_dir) {
+        let key = match by {
+            "ext" => Path::new(&n.name).extension()
+                .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+                .unwrap_or_else(|| "(none)".into()),
+            "age" => {
+                let days = now.duration_since(n.mtime).map(|d| d.as_secs() / 86400).unwrap_or(0);
+                AGE_BUCKETS.iter().find(|(_, max)| days < *max).map_or("", |b| b.0).to_string()
+            }
+            "owner" => users.get(&n.owner).cloned().unwrap_or_else(|| n.owner.to_string()),
+            _ => format!("depth {}", depth(i)),
+        };
+        let b = buckets.entry(key).or_default();
+        b.0 += 1;
+        b.1 += n.size;
     }
 
-    println!("\n  Diff: {}  vs  {}\n",
-        dir_a.display().to_string().cyan(),
-        dir_b.display().to_string().cyan());
+    let mut rows: Vec<_> = buckets.into_iter().collect();
+    rows.sort_by(|a, b| b.1.1.cmp(&a.1.1).then_with(|| a.0.cmp(&b.0)));
+    let total: u64 = rows.iter().map(|r| r.1.1).sum();
+    let width = rows.iter().map(|r| r.0.chars().count()).max().unwrap_or(0).clamp(6, 40);
 
-    for f in only_a  { println!("  {}  {}", "only in A".red(),     f.dimmed()); }
-    for f in only_b  { println!("  {}  {}", "only in B".green(),   f.dimmed()); }
-    for f in changed { println!("  {}   {}", "modified".yellow(), f.dimmed()); }
-    println!();
+    println!("\n  {:<width$}  {:>8}  {:>10}  {}", by.to_uppercase().bold().underline(), "FILES".bold().underline(),
+        "SIZE".bold().underline(), "SHARE".bold().underline());
+    for (key, (files, size)) in rows.iter().take(top) {
+        let share = if total > 0 { *size as f64 * 100.0 / total as f64 } else { 0.0 };
+        println!("  {:<width$}  {:>8}  {:>10}  {} {:>5.1}%", key.cyan(), files, human_size(*size).yellow(),
+            size_bar(*size, total, 16).cyan(), share);
+    }
+    if rows.len() > top {
+        let (files, size) = rows[top..].iter().fold((0, 0), |acc, r| (acc.0 + r.1.0, acc.1 + r.1.1));
+        println!("  {:<width$}  {:>8}  {:>10}", format!("… {} more", rows.len() - top).dimmed(), files, human_size(size).dimmed());
+    }
 }
 
-// ─────────────────────────────────────────────────────────────
-//  DUPE
-// ─────────────────────────────────────────────────────────────
-fn cmd_dupe(directory: &Path) {
-    use std::collections::HashMap;
-
-    let mut hashes: HashMap<String, Vec<PathBuf>> = HashMap::new();
+fn print_size_level(nodes: &[SizeNode], dir: usize, prefix: &str, depth: usize, top: usize, total: u64) {
+    let mut children = nodes[dir].children.clone();
+    children.sort_by_key(|&i| std::cmp::Reverse(nodes[i].size));
+    let rest: Vec<usize> = children.split_off(top.min(children.len()));
+    let nested = dir != 0;
 
-    print!("Scanning… ");
-    for entry in walkdir::WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-        if !entry.file_type().is_file() { continue; }
-        let path = entry.into_path();
-        if let Ok(data) = std::fs::read(&path) {
-            let hash = format!("{:x}", md5::compute(&data));
-            hashes.entry(hash).or_default().push(path);
+    for (pos, &i) in children.iter().enumerate() {
+        let n = &nodes[i];
+        let last = pos + 1 == children.len() && rest.is_empty();
+        let connector = match (nested, last) { (false, _) => "", (true, true) => "└── ", (true, false) => "├── " };
+        let share = if total > 0 { n.size as f64 * 100.0 / total as f64 } else { 0.0 };
+        let icon = if n.is_dir { "📁" } else { "📄" };
+        let name = if n.is_dir { n.name.blue().bold() } else { n.name.white() };
+        println!("  {:>10}  {} {:>5.1}%  {}{}{icon}  {}",
+            human_size(n.size).yellow(), size_bar(n.size, total, 16).cyan(), share, prefix.dimmed(), connector.dimmed(), name);
+        if n.is_dir && depth > 1 {
+            let extension = match (nested, last) { (false, _) => "", (true, true) => "    ", (true, false) => "│   " };
+            print_size_level(nodes, i, &format!("{prefix}{extension}"), depth - 1, top, total);
+        }
+    }
+    if !rest.is_empty() {
+        let size: u64 = rest.iter().map(|&i| nodes[i].size).sum();
+        let connector = if nested { "└── " } else { "" };
+        println!("  {:>10}  {:<23}  {}{}{}", human_size(size).dimmed(), "", prefix.dimmed(), connector.dimmed(),
+            format!("… {} more", rest.len()).dimmed());
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    if path.is_file() {
+        return path.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
+        .sum()
+}
+
+// ─────────────────────────────────────────────────────────────
+//  SIZE BROWSER
+// ─────────────────────────────────────────────────────────────
+/// One file or directory from a size scan; directories carry the totals of everything below
+struct SizeNode {
+    path: PathBuf,
+    name: String,
+    size: u64,
+    files: u64,
+    /// newest modification time anywhere inside
+    mtime: std::time::SystemTime,
+    is_dir: bool,
+    /// numeric user id of the owner (0 where the platform has none)
+    owner: u32,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Walk `root` once and keep every entry in an arena (index 0 is the root), so drilling
+/// down never has to touch the disk again. Symlinks are counted, not followed.
+fn scan_tree(root: &Path, opts: SizeOpts, progress: &std::sync::atomic::AtomicU64) -> Vec<SizeNode> {
+    let mut nodes = Vec::new();
+    let Ok(meta) = std::fs::symlink_metadata(root) else { return nodes };
+    let mut scan = SizeScan {
+        opts,
+        seen: std::collections::HashSet::new(),
+        root_dev: file_device(&meta),
+        progress,
+        pending: Vec::new(),
+    };
+    scan.node(&mut nodes, root.to_path_buf(), &meta, None);
+    // folders waiting to be read, so deep trees don't recurse once per level
+    while let Some(id) = scan.pending.pop() {
+        let Ok(rd) = std::fs::read_dir(&nodes[id].path) else { continue };
+        for entry in rd.filter_map(|e| e.ok()) {
+            let Ok(meta) = entry.metadata() else { continue };
+            let child = scan.node(&mut nodes, entry.path(), &meta, Some(id));
+            nodes[id].children.push(child);
+        }
+    }
+    // a node always comes after its folder, so one backwards pass rolls every total up
+    for id in (1..nodes.len()).rev() {
+        let (size, files, mtime) = (nodes[id].size, nodes[id].files, nodes[id].mtime);
+        let Some(parent) = nodes[id].parent else { continue };
+        let dir = &mut nodes[parent];
+        dir.size += size;
+        dir.files += files;
+        dir.mtime = dir.mtime.max(mtime);
+    }
+    nodes
+}
+
+struct SizeScan<'a> {
+    opts: SizeOpts,
+    /// (device, inode) of hard-linked files already counted once
+    seen: std::collections::HashSet<(u64, u64)>,
+    root_dev: u64,
+    progress: &'a std::sync::atomic::AtomicU64,
+    /// folders added but not read yet
+    pending: Vec<usize>,
+}
+
+impl SizeScan<'_> {
+    /// Add one entry with its own size; folders are queued to be read and get their totals later
+    fn node(&mut self, nodes: &mut Vec<SizeNode>, path: PathBuf, meta: &std::fs::Metadata, parent: Option<usize>) -> usize {
+        let id = nodes.len();
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+        let is_dir = meta.is_dir();
+        let counted = hard_link_id(meta).is_none_or(|key| self.seen.insert(key));
+        let size = match (counted, self.opts.apparent) {
+            (false, _)    => 0,
+            (true, true)  => if is_dir { 0 } else { meta.len() },
+            (true, false) => allocated_size(meta),
+        };
+        nodes.push(SizeNode {
+            name,
+            size,
+            files: if is_dir { 0 } else { 1 },
+            mtime: meta.modified().unwrap_or(std::time::UNIX_EPOCH),
+            is_dir,
+            owner: file_owner(meta),
+            parent,
+            children: Vec::new(),
+            path,
+        });
+        self.progress.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        if is_dir && !(self.opts.one_file_system && file_device(meta) != self.root_dev) {
+            self.pending.push(id);
+        }
+        id
+    }
+}
+
+/// Bytes actually allocated on disk (sparse files and small files differ from their length)
+#[cfg(unix)]
+fn allocated_size(meta: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks() * 512
+}
+#[cfg(not(unix))]
+fn allocated_size(meta: &std::fs::Metadata) -> u64 {
+    if meta.is_dir() { 0 } else { meta.len() }
+}
+
+#[cfg(unix)]
+fn file_device(meta: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.dev()
+}
+#[cfg(not(unix))]
+fn file_device(_meta: &std::fs::Metadata) -> u64 { 0 }
+
+#[cfg(unix)]
+fn file_owner(meta: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+    meta.uid()
+}
+#[cfg(not(unix))]
+fn file_owner(_meta: &std::fs::Metadata) -> u32 { 0 }
+
+/// uid → login name from /etc/passwd; unknown ids fall back to the number
+fn user_names() -> std::collections::HashMap<u32, String> {
+    std::fs::read_to_string("/etc/passwd")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| {
+            let mut f = l.split(':');
+            let name = f.next()?;
+            let uid = f.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// (device, inode) for files with more than one hard link, so each is counted once
+#[cfg(unix)]
+fn hard_link_id(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (!meta.is_dir() && meta.nlink() > 1).then(|| (meta.dev(), meta.ino()))
+}
+#[cfg(not(unix))]
+fn hard_link_id(_meta: &std::fs::Metadata) -> Option<(u64, u64)> { None }
+
+#[cfg(not(target_os = "android"))]
+fn move_to_trash(path: &Path) -> Result<(), String> {
+    trash::delete(path).map_err(|e| e.to_string())
+}
+#[cfg(target_os = "android")]
+fn move_to_trash(_path: &Path) -> Result<(), String> {
+    Err("trash isn't available on Android".to_string())
+}
+
+/// Scan in a worker thread while a spinner shows how far it got
+fn scan_tree_with_progress(root: &Path, opts: SizeOpts) -> Vec<SizeNode> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let progress = AtomicU64::new(0);
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(indicatif::ProgressStyle::with_template("{spinner:.cyan} {msg}").unwrap());
+    std::thread::scope(|s| {
+        let worker = s.spawn(|| scan_tree(root, opts, &progress));
+        while !worker.is_finished() {
+            spinner.set_message(format!("Scanning {}… {} entries", root.display(), progress.load(Ordering::Relaxed)));
+            spinner.tick();
+            std::thread::sleep(std::time::Duration::from_millis(80));
+        }
+        spinner.finish_and_clear();
+        worker.join().unwrap()
+    })
+}
+
+/// A `width`-column bar showing `part / whole`, drawn in eighths of a cell
+fn size_bar(part: u64, whole: u64, width: usize) -> String {
+    let eighths = if whole == 0 { 0 } else { (part as f64 / whole as f64 * (width * 8) as f64).round() as usize };
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths % 8 > 0 {
+        bar.push([' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'][eighths % 8]);
+    }
+    format!("{bar:<width$}")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SizeSort { Size, Count, Mtime }
+
+struct SizeBrowser {
+    nodes: Vec<SizeNode>,
+    dir: usize,
+    cursor: usize,
+    offset: usize,
+    sort: SizeSort,
+    status: String,
+}
+
+impl SizeBrowser {
+    fn entries(&self) -> Vec<usize> {
+        let mut list = self.nodes[self.dir].children.clone();
+        match self.sort {
+            SizeSort::Size  => list.sort_by_key(|&i| std::cmp::Reverse(self.nodes[i].size)),
+            SizeSort::Count => list.sort_by_key(|&i| std::cmp::Reverse(self.nodes[i].files)),
+            SizeSort::Mtime => list.sort_by_key(|&i| std::cmp::Reverse(self.nodes[i].mtime)),
+        }
+        list
+    }
+
+    /// Drop a node from the tree and take its totals off every ancestor
+    fn remove(&mut self, id: usize) {
+        let (size, files) = (self.nodes[id].size, self.nodes[id].files);
+        let mut up = self.nodes[id].parent;
+        while let Some(p) = up {
+            self.nodes[p].size -= size;
+            self.nodes[p].files -= files;
+            up = self.nodes[p].parent;
+        }
+        if let Some(p) = self.nodes[id].parent {
+            self.nodes[p].children.retain(|&c| c != id);
+        }
+    }
+
+    fn render(&mut self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        use crossterm::{cursor::MoveTo, queue, terminal::{self, Clear, ClearType}};
+
+        let (w, h) = terminal::size().unwrap_or((80, 24));
+        let (w, rows) = (w as usize, (h as usize).saturating_sub(4).max(1));
+        let entries = self.entries();
+        self.cursor = self.cursor.min(entries.len().saturating_sub(1));
+        if self.cursor < self.offset { self.offset = self.cursor; }
+        if self.cursor >= self.offset + rows { self.offset = self.cursor + 1 - rows; }
+
+        let cut = |s: String| s.chars().take(w).collect::<String>();
+        let here = &self.nodes[self.dir];
+        let sort = match self.sort { SizeSort::Size => "size", SizeSort::Count => "count", SizeSort::Mtime => "modified" };
+        queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+        write!(out, "{}\r\n", cut(format!(" {}  {}  ·  {} files  ·  sorted by {}",
+            here.path.display(), human_size(here.size), here.files, sort)).bold())?;
+        write!(out, "{}\r\n", "─".repeat(w).dimmed())?;
+
+        for (row, &i) in entries.iter().enumerate().skip(self.offset).take(rows) {
+            let n = &self.nodes[i];
+            let share = if here.size > 0 { n.size as f64 / here.size as f64 } else { 0.0 };
+            let date: chrono::DateTime<chrono::Local> = n.mtime.into();
+            let line = cut(format!(" {:>10} {:>5.1}% [{}] {:>8}  {}  {}{}",
+                human_size(n.size), share * 100.0, size_bar(n.size, here.size, 10), n.files,
+                date.format("%Y-%m-%d"), n.name, if n.is_dir { "/" } else { "" }));
+            let line = format!("{line:<w$}");
+            if row == self.cursor {
+                write!(out, "{}\r\n", line.reversed())?;
+            } else if n.is_dir {
+                write!(out, "{}\r\n", line.cyan())?;
+            } else {
+                write!(out, "{line}\r\n")?;
+            }
+        }
+        if entries.is_empty() {
+            write!(out, "{}\r\n", "  (empty)".dimmed())?;
+        }
+
+        queue!(out, MoveTo(0, h.saturating_sub(1)))?;
+        let help = if cfg!(target_os = "android") {
+            "↑↓ move  ⏎/→ open  ← back  s/c/m sort  d delete  q quit"
+        } else {
+            "↑↓ move  ⏎/→ open  ← back  s/c/m sort  d delete  t trash  q quit"
+        };
+        let status = if self.status.is_empty() { help.dimmed() } else { self.status.as_str().yellow() };
+        write!(out, "{}", cut(format!(" {status}")))?;
+        out.flush()
+    }
+
+    /// Ask a yes/no question on the status line
+    fn ask(&mut self, out: &mut impl std::io::Write, question: String) -> std::io::Result<bool> {
+        use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+        self.status = question;
+        self.render(out)?;
+        self.status.clear();
+        loop {
+            if let Event::Key(k) = event::read()? {
+                if k.kind != KeyEventKind::Press { continue; }
+                return Ok(matches!(k.code, KeyCode::Char('y') | KeyCode::Char('Y')));
+            }
+        }
+    }
+
+    fn delete(&mut self, out: &mut impl std::io::Write, trash: bool) -> std::io::Result<()> {
+        let Some(&id) = self.entries().get(self.cursor) else { return Ok(()) };
+        let n = &self.nodes[id];
+        let verb = if trash { "Move to trash" } else { "Delete" };
+        let question = format!("{verb} {}{} ({})? [y/N]", n.name, if n.is_dir { "/" } else { "" }, human_size(n.size));
+        if !self.ask(out, question)? { return Ok(()); }
+
+        let path = self.nodes[id].path.clone();
+        let result = if trash {
+            move_to_trash(&path)
+        } else if self.nodes[id].is_dir {
+            std::fs::remove_dir_all(&path).map_err(|e| e.to_string())
+        } else {
+            std::fs::remove_file(&path).map_err(|e| e.to_string())
+        };
+        match result {
+            Ok(()) => {
+                self.status = format!("✓ {} {}", if trash { "Trashed" } else { "Deleted" }, path.display());
+                self.remove(id);
+            }
+            Err(e) => self.status = format!("✗ {}: {}", path.display(), e),
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+        loop {
+            self.render(out)?;
+            let Event::Key(k) = event::read()? else { continue };
+            if k.kind != KeyEventKind::Press { continue; }
+            self.status.clear();
+            let entries = self.entries();
+            match k.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k')   => self.cursor = self.cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => self.cursor += 1,
+                KeyCode::PageUp   => self.cursor = self.cursor.saturating_sub(10),
+                KeyCode::PageDown => self.cursor += 10,
+                KeyCode::Home => self.cursor = 0,
+                KeyCode::End  => self.cursor = entries.len().saturating_sub(1),
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                    if let Some(&i) = entries.get(self.cursor).filter(|&&i| self.nodes[i].is_dir) {
+                        self.dir = i;
+                        self.cursor = 0;
+                        self.offset = 0;
+                    }
+                }
+                KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => {
+                    if let Some(parent) = self.nodes[self.dir].parent {
+                        let came_from = self.dir;
+                        self.dir = parent;
+                        self.cursor = self.entries().iter().position(|&i| i == came_from).unwrap_or(0);
+                    }
+                }
+                KeyCode::Char('s') => self.sort = SizeSort::Size,
+                KeyCode::Char('c') => self.sort = SizeSort::Count,
+                KeyCode::Char('m') => self.sort = SizeSort::Mtime,
+                KeyCode::Char('d') | KeyCode::Delete => self.delete(out, false)?,
+                #[cfg(not(target_os = "android"))]
+                KeyCode::Char('t') => self.delete(out, true)?,
+                #[cfg(target_os = "android")]
+                KeyCode::Char('t') => self.status = "! Trash isn't available on Android, use d to delete".to_string(),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn cmd_size_browse(directory: &Path, opts: SizeOpts) {
+    use crossterm::{cursor, execute, terminal};
+
+    let root = directory.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Not found: {}", "✗".red(), directory.display());
+        std::process::exit(1);
+    });
+    let nodes = scan_tree_with_progress(&root, opts);
+    let mut browser = SizeBrowser { nodes, dir: 0, cursor: 0, offset: 0, sort: SizeSort::Size, status: String::new() };
+
+    let mut out = std::io::stdout();
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(out, terminal::EnterAlternateScreen, cursor::Hide))
+        .and_then(|_| browser.run(&mut out));
+    let _ = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+    if let Err(e) = result {
+        eprintln!("{} {}", "✗".red(), e);
+        std::process::exit(1);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  SIZE HISTORY
+// ─────────────────────────────────────────────────────────────
+/// How deep below the root a snapshot remembers individual items
+const SIZE_HISTORY_DEPTH: usize = 3;
+
+#[derive(Args, Clone, Default)]
+struct SizeHistoryArgs {
+    /// Record this scan in the size history, optionally under a label
+    #[arg(long, num_args = 0..=1, default_missing_value = "", value_name = "LABEL",
+          conflicts_with_all = ["files", "dirs", "interactive"])]
+    save: Option<String>,
+    /// Show what grew or shrank since a snapshot (id, label, "latest", a date like 2026-10-01, or an age like 7d)
+    #[arg(long, value_name = "SNAPSHOT|DATE", conflicts_with_all = ["by", "files", "dirs", "interactive"])]
+    since: Option<String>,
+    /// List saved snapshots of this directory
+    #[arg(long, conflicts_with_all = ["save", "since", "by", "files", "dirs", "interactive"])]
+    history: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SizeSnapshot {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    /// unix seconds
+    time: i64,
+    root: PathBuf,
+    apparent: bool,
+    total: u64,
+    files: u64,
+    /// path relative to the root → size, for everything up to SIZE_HISTORY_DEPTH deep
+    items: std::collections::BTreeMap<String, u64>,
+}
+
+/// `$VASU_SIZE_HISTORY`, else `$XDG_DATA_HOME/vasu/size-history.jsonl`, else `~/.local/share/vasu/size-history.jsonl`
+fn size_history_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("VASU_SIZE_HISTORY").filter(|p| !p.is_empty()) {
+        return PathBuf::from(p);
+    }
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".local").join("share")))
+        .unwrap_or_default()
+        .join("vasu")
+        .join("size-history.jsonl")
+}
+
+/// Snapshots of `root`, oldest first
+fn load_size_history(root: &Path) -> Vec<SizeSnapshot> {
+    std::fs::read_to_string(size_history_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str::<SizeSnapshot>(l).ok())
+        .filter(|s| s.root == root)
+        .collect()
+}
+
+/// Relative path → size for every node down to `max_depth`
+fn size_items(nodes: &[SizeNode], max_depth: usize) -> std::collections::BTreeMap<String, u64> {
+    fn walk(nodes: &[SizeNode], dir: usize, prefix: &str, left: usize, out: &mut std::collections::BTreeMap<String, u64>) {
+        for &c in &nodes[dir].children {
+            let rel = if prefix.is_empty() { nodes[c].name.clone() } else { format!("{prefix}/{}", nodes[c].name) };
+            out.insert(rel.clone(), nodes[c].size);
+            if left > 1 && nodes[c].is_dir {
+                walk(nodes, c, &rel, left - 1, out);
+            }
+        }
+    }
+    let mut out = std::collections::BTreeMap::new();
+    if !nodes.is_empty() {
+        walk(nodes, 0, "", max_depth, &mut out);
+    }
+    out
+}
+
+fn save_size_snapshot(root: &Path, nodes: &[SizeNode], apparent: bool, label: &str) {
+    use std::io::Write;
+
+    let now = chrono::Local::now();
+    let mut id = now.format("%Y%m%d-%H%M%S").to_string();
+    let taken = load_size_history(root);
+    if taken.iter().any(|s| s.id == id) {
+        id = (2..).map(|n| format!("{id}-{n}")).find(|c| !taken.iter().any(|s| &s.id == c)).unwrap();
+    }
+    let snap = SizeSnapshot {
+        id,
+        label: (!label.is_empty()).then(|| label.to_string()),
+        time: now.timestamp(),
+        root: root.to_path_buf(),
+        apparent,
+        total: nodes[0].size,
+        files: nodes[0].files,
+        items: size_items(nodes, SIZE_HISTORY_DEPTH),
+    };
+    let path = size_history_path();
+    let result = std::fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+        .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut f| writeln!(f, "{}", serde_json::to_string(&snap).unwrap()));
+    match result {
+        Ok(()) => println!("{} Saved size snapshot {} to {}", "✓".green().bold(), snap.id.cyan(), path.display()),
+        Err(e) => {
+            eprintln!("{} Cannot write {}: {}", "✗".red(), path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Resolve `--since`: a snapshot id or label, "latest", a date (YYYY-MM-DD, end of that day)
+/// or an age like 7d — the latter two pick the newest snapshot taken no later than that
+fn find_size_snapshot<'a>(snaps: &'a [SizeSnapshot], spec: &str) -> Option<&'a SizeSnapshot> {
+    if spec == "latest" || spec == "last" {
+        return snaps.last();
+    }
+    if let Some(s) = snaps.iter().rev().find(|s| s.id == spec || s.label.as_deref() == Some(spec)) {
+        return Some(s);
+    }
+    let cutoff = chrono::NaiveDate::parse_from_str(spec, "%Y-%m-%d").ok()
+        .and_then(|d| d.and_hms_opt(23, 59, 59))
+        .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        .map(|t| t.timestamp())
+        .or_else(|| parse_age(spec).ok().map(|age| chrono::Local::now().timestamp() - age.as_secs() as i64))?;
+    snaps.iter().rev().find(|s| s.time <= cutoff)
+}
+
+fn fmt_snapshot_time(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn fmt_size_change(change: i64) -> ColoredString {
+    let text = format!("{}{}", if change < 0 { "-" } else { "+" }, human_size(change.unsigned_abs()));
+    match change.cmp(&0) {
+        std::cmp::Ordering::Greater => text.red(),
+        std::cmp::Ordering::Less => text.green(),
+        std::cmp::Ordering::Equal => text.dimmed(),
+    }
+}
+
+fn print_size_since(root: &Path, nodes: &[SizeNode], spec: &str, depth: usize, top: usize, apparent: bool) {
+    let snaps = load_size_history(root);
+    let Some(then) = find_size_snapshot(&snaps, spec) else {
+        eprintln!("{} No size snapshot of {} matches '{}' (save one with --save)", "✗".red(), root.display(), spec);
+        std::process::exit(1);
+    };
+    if then.apparent != apparent {
+        eprintln!("{} Snapshot {} counted {} sizes; comparing anyway", "!".yellow(), then.id,
+            if then.apparent { "apparent" } else { "allocated" });
+    }
+    let depth = if depth > SIZE_HISTORY_DEPTH {
+        eprintln!("{} Snapshots only remember {} levels", "!".yellow(), SIZE_HISTORY_DEPTH);
+        SIZE_HISTORY_DEPTH
+    } else {
+        depth.max(1)
+    };
+
+    let now = size_items(nodes, depth);
+    let before: std::collections::BTreeMap<&str, u64> = then.items.iter()
+        .filter(|(k, _)| k.split('/').count() <= depth)
+        .map(|(k, v)| (k.as_str(), *v))
+        .collect();
+    let mut rows: Vec<(&str, Option<u64>, Option<u64>)> = now.iter()
+        .map(|(k, v)| (k.as_str(), Some(*v), before.get(k.as_str()).copied()))
+        .chain(before.iter().filter(|(k, _)| !now.contains_key(**k)).map(|(k, v)| (*k, None, Some(*v))))
+        .filter(|(_, n, b)| n != b)
+        .collect();
+    let change = |r: &(&str, Option<u64>, Option<u64>)| r.1.unwrap_or(0) as i64 - r.2.unwrap_or(0) as i64;
+    rows.sort_by(|a, b| change(b).abs().cmp(&change(a).abs()).then_with(|| a.0.cmp(b.0)));
+
+    let label = then.label.as_deref().map(|l| format!(" \"{l}\"")).unwrap_or_default();
+    println!("\n  Since {} (snapshot {}{})", fmt_snapshot_time(then.time).bold(), then.id.cyan(), label);
+    if rows.is_empty() {
+        println!("\n  {}", "No changes.".dimmed());
+    } else {
+        println!("\n  {:<10}  {:<10}  {:<10}  {}", "CHANGE".bold().underline(), "NOW".bold().underline(),
+            "BEFORE".bold().underline(), "ITEM".bold().underline());
+        for r in rows.iter().take(top) {
+            let (now, before) = (r.1.map(human_size), r.2.map(human_size));
+            let item = match (r.1, r.2) {
+                (Some(_), None) => format!("{}  {}", r.0, "new".yellow()),
+                (None, Some(_)) => format!("{}  {}", r.0, "gone".dimmed()),
+                _ => r.0.to_string(),
+            };
+            println!("  {:<10}  {:<10}  {:<10}  {}", fmt_size_change(change(r)),
+                now.unwrap_or_else(|| "—".into()), before.unwrap_or_else(|| "—".into()), item);
+        }
+        if rows.len() > top { println!("  {}", format!("… {} more changed", rows.len() - top).dimmed()); }
+    }
+    println!("\n  Total {} → {} ({})", human_size(then.total), human_size(nodes[0].size).bold(),
+        fmt_size_change(nodes[0].size as i64 - then.total as i64));
+}
+
+fn cmd_size_history(directory: &Path) {
+    let root = directory.canonicalize().unwrap_or(directory.to_path_buf());
+    let snaps = load_size_history(&root);
+    if snaps.is_empty() {
+        println!("{}", format!("No size snapshots of {} yet (save one with --save)", root.display()).dimmed());
+        return;
+    }
+    println!("\n  {:<19} {:<18} {:<12} {:<10} {}", "ID".bold().underline(), "TAKEN".bold().underline(),
+        "LABEL".bold().underline(), "SIZE".bold().underline(), "CHANGE".bold().underline());
+    let mut prev: Option<u64> = None;
+    for s in &snaps {
+        let change = prev.map(|p| fmt_size_change(s.total as i64 - p as i64).to_string()).unwrap_or_default();
+        println!("  {:<19} {:<18} {:<12} {:<10} {}", s.id.cyan(), fmt_snapshot_time(s.time),
+            s.label.as_deref().unwrap_or(""), human_size(s.total).yellow(), change);
+        prev = Some(s.total);
+    }
+    println!();
+}
+
+// ─────────────────────────────────────────────────────────────
+//  CLEAN
+// ─────────────────────────────────────────────────────────────
+/// Built-in presets: (name, marker files that identify the project type, rules).
+/// A rule is an exact file/dir name, or `*.ext` for a suffix. Name rules of a preset with
+/// markers only fire when a marker sits right next to the match (`target/` beside `Cargo.toml`).
+const CLEAN_PRESETS: &[(&str, &[&str], &[&str])] = &[
+    ("general", &[], &[
+        ".DS_Store", "Thumbs.db", "__pycache__", ".pytest_cache", ".mypy_cache", ".ruff_cache",
+        "*.pyc", "*.pyo", "*.o", "*.obj",
+    ]),
+    ("logs",    &[], &["*.log"]),
+    ("rust",    &["Cargo.toml"], &["target"]),
+    ("node",    &["package.json"], &[
+        "node_modules", ".next", ".nuxt", ".svelte-kit", ".turbo", ".parcel-cache", "dist", "coverage",
+    ]),
+    ("python",  &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"], &[
+        ".venv", ".tox", ".nox", ".eggs", "*.egg-info", "build", "dist", "htmlcov", ".coverage",
+    ]),
+    ("java",    &["pom.xml", "build.gradle", "build.gradle.kts"], &["target", "build", ".gradle", "*.class"]),
+    ("jvm",     &["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts", "build.sbt"], &[
+        "build", "target", ".gradle", ".bsp", ".kotlin", "*.class",
+    ]),
+    ("go",      &["go.mod"], &["*.test", "*.prof", "coverage.out"]),
+];
+
+struct CleanPreset {
+    name: String,
+    markers: Vec<String>,
+    rules: Vec<String>,
+    /// Active everywhere (general, custom rules, or picked with --preset) rather than via markers
+    always: bool,
+}
+
+fn clean_rule_matches(rule: &str, name: &str) -> bool {
+    match rule.strip_prefix('*') {
+        Some(suffix) => name.ends_with(suffix),
+        None => name == rule,
+    }
+}
+
+/// Built-in presets plus `[clean.preset.<name>]` from config (which may override a built-in),
+/// with `--preset` / `clean.presets` deciding which are on everywhere and which wait for markers.
+fn clean_presets(selected: &[String]) -> Vec<CleanPreset> {
+    let cfg = &config().clean;
+    let mut presets: Vec<CleanPreset> = CLEAN_PRESETS.iter()
+        .map(|(name, markers, rules)| CleanPreset {
+            name: name.to_string(),
+            markers: markers.iter().map(|m| m.to_string()).collect(),
+            rules: rules.iter().map(|r| r.to_string()).collect(),
+            always: false,
+        })
+        .collect();
+    for (name, user) in &cfg.preset {
+        let preset = CleanPreset { name: name.clone(), markers: user.markers.clone(), rules: user.rules.clone(), always: false };
+        match presets.iter_mut().find(|p| p.name == *name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+
+    let selected = if selected.is_empty() { &cfg.presets } else { selected };
+    let auto = selected.iter().any(|s| s == "auto");
+    for name in selected.iter().filter(|s| *s != "auto") {
+        match presets.iter_mut().find(|p| p.name == *name) {
+            Some(p) => p.always = true,
+            None => {
+                let known: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+                eprintln!("{} Unknown preset '{}' (available: auto, {})", "✗".red(), name, known.join(", "));
+                std::process::exit(1);
+            }
+        }
+    }
+    // general is always on; other marker-less presets (logs, …) only when picked
+    for p in presets.iter_mut().filter(|p| p.name == "general") {
+        p.always = true;
+    }
+    presets.retain(|p| p.always || (auto && !p.markers.is_empty()));
+    if !cfg.rules.is_empty() {
+        presets.push(CleanPreset { name: "custom".into(), markers: Vec::new(), rules: cfg.rules.clone(), always: true });
+    }
+    presets
+}
+
+fn print_clean_rules(directory: &Path, presets: &[CleanPreset]) {
+    println!();
+    for p in presets {
+        let active = if p.always {
+            "always".green()
+        } else if p.markers.iter().any(|m| directory.join(m).is_file()) {
+            "detected here".green()
+        } else {
+            "when a marker is found".dimmed()
+        };
+        println!("  {:<10} {}", p.name.yellow().bold(), active);
+        if !p.markers.is_empty() {
+            println!("      {} {}", "markers:".dimmed(), p.markers.join(", ").cyan());
+        }
+        println!("      {} {}", "rules:  ".dimmed(), p.rules.join(", "));
+    }
+    println!();
+}
+
+struct CleanHit {
+    path: PathBuf,
+    /// "preset · rule", used to group the size report
+    rule: String,
+    size: u64,
+}
+
+/// Total size and the newest modification time of anything inside `path`
+fn size_and_newest(path: &Path) -> (u64, std::time::SystemTime) {
+    let mut size = 0;
+    let mut newest = std::time::UNIX_EPOCH;
+    for e in walkdir::WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+        let Ok(meta) = e.metadata() else { continue };
+        if meta.is_file() { size += meta.len(); }
+        if let Ok(m) = meta.modified() { newest = newest.max(m); }
+    }
+    (size, newest)
+}
+
+fn collect_clean_hits(directory: &Path, presets: &[CleanPreset], older_than: Option<std::time::Duration>) -> Vec<CleanHit> {
+    use std::collections::HashMap;
+    use walkdir::WalkDir;
+
+    let markers_in = |dir: &Path| -> Vec<usize> {
+        (0..presets.len())
+            .filter(|&i| !presets[i].always && presets[i].markers.iter().any(|m| dir.join(m).is_file()))
+            .collect()
+    };
+    let always: Vec<usize> = (0..presets.len()).filter(|&i| presets[i].always).collect();
+
+    // presets switched on per directory: inherited from the parent plus any whose marker sits here
+    let mut active: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    let mut found: Vec<(PathBuf, String)> = Vec::new();
+
+    let mut walker = WalkDir::new(directory).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        let parent = path.parent().unwrap_or(Path::new(""));
+        let inherited = active.get(parent).cloned().unwrap_or_else(|| always.clone());
+        if entry.file_type().is_dir() {
+            let mut here = inherited.clone();
+            here.extend(markers_in(path).into_iter().filter(|i| !inherited.contains(i)));
+            active.insert(path.to_path_buf(), here);
+        }
+        if entry.depth() == 0 { continue; }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        let hit = inherited.iter().find_map(|&i| {
+            let p = &presets[i];
+            p.rules.iter()
+                .filter(|r| clean_rule_matches(r, &name))
+                // generic names like target/ or build/ need their project file right beside them
+                .find(|r| r.starts_with('*') || p.markers.is_empty()
+                    || p.markers.iter().any(|m| parent.join(m).is_file()))
+                .map(|r| format!("{} · {}", p.name, r))
+        });
+        if let Some(rule) = hit {
+            // a matched directory goes as a whole, so there's no point walking into it
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+                active.remove(path);
+            }
+            found.push((entry.into_path(), rule));
+        }
+    }
+
+    let cutoff = older_than.map(|age| std::time::SystemTime::now() - age);
+    let mut tracked = GitTracked::default();
+    found.into_iter()
+        .filter(|(path, _)| !tracked.contains(path))
+        .filter_map(|(path, rule)| {
+            let (size, newest) = size_and_newest(&path);
+            if cutoff.is_some_and(|c| newest > c) { return None; }
+            Some(CleanHit { path, rule, size })
+        })
+        .collect()
+}
+
+/// Files tracked by git, per repository, so `clean` never deletes committed content
+#[derive(Default)]
+struct GitTracked {
+    /// repo root → tracked files and every directory that contains one
+    repos: std::collections::HashMap<PathBuf, Option<std::collections::HashSet<PathBuf>>>,
+}
+
+impl GitTracked {
+    fn contains(&mut self, path: &Path) -> bool {
+        let Some(root) = path.ancestors().skip(1).find(|a| a.join(".git").exists()) else { return false };
+        let tracked = self.repos.entry(root.to_path_buf()).or_insert_with(|| {
+            let out = std::process::Command::new("git")
+                .arg("-C").arg(root)
+                .args(["ls-files", "-z"])
+                .stderr(std::process::Stdio::null())
+                .output()
+                .ok()
+                .filter(|o| o.status.success())?;
+            let mut set = std::collections::HashSet::new();
+            for rel in out.stdout.split(|b| *b == 0).filter(|r| !r.is_empty()) {
+                let abs = root.join(String::from_utf8_lossy(rel).as_ref());
+                set.extend(abs.ancestors().take_while(|a| *a != root).map(Path::to_path_buf));
+            }
+            Some(set)
+        });
+        tracked.as_ref().is_some_and(|set| set.contains(path))
+    }
+}
+
+fn print_clean_summary(title: &str, hits: &[&CleanHit]) {
+    let mut by_rule: std::collections::BTreeMap<&str, (usize, u64)> = std::collections::BTreeMap::new();
+    for h in hits {
+        let e = by_rule.entry(&h.rule).or_default();
+        e.0 += 1;
+        e.1 += h.size;
+    }
+    let mut rows: Vec<_> = by_rule.into_iter().collect();
+    rows.sort_by_key(|r| std::cmp::Reverse(r.1.1));
+
+    println!("\n{}", title.bold());
+    println!("  {:<28} {:<8} {}", "RULE".bold().underline(), "ITEMS".bold().underline(), "SIZE".bold().underline());
+    for (rule, (n, size)) in &rows {
+        println!("  {:<28} {:<8} {}", rule.cyan(), n, human_size(*size).yellow());
+    }
+    let total: u64 = hits.iter().map(|h| h.size).sum();
+    println!("  {:<28} {:<8} {}", "TOTAL".bold(), hits.len().to_string().bold(), human_size(total).yellow().bold());
+}
+
+fn cmd_clean(
+    directory: &Path, yes: bool, preset: &[String], list_rules: bool,
+    older_than: Option<std::time::Duration>, scan: bool,
+) {
+    let presets = clean_presets(preset);
+    if list_rules {
+        print_clean_rules(directory, &presets);
+        return;
+    }
+
+    let base = directory.canonicalize().unwrap_or(directory.to_path_buf());
+    let found = collect_clean_hits(&base, &presets, older_than);
+    if found.is_empty() {
+        println!("{}", "Nothing to clean — already spotless! ✨".green());
+        return;
+    }
+    if scan {
+        return cmd_clean_scan(&base, &presets, &found, yes);
+    }
+
+    println!("\n{} {} item(s):", "Will remove".yellow().bold(), found.len());
+    for h in found.iter().take(15) {
+        let rel = h.path.strip_prefix(&base).unwrap_or(&h.path);
+        println!("  {:<10} {}  {}", human_size(h.size).dimmed(), rel.display().to_string().red(),
+            format!("({})", h.rule).dimmed());
+    }
+    if found.len() > 15 { println!("  … and {} more", found.len() - 15); }
+    print_clean_summary("Reclaimable per rule:", &found.iter().collect::<Vec<_>>());
+
+    if !yes && !confirm("\nProceed?") { return; }
+    remove_clean_hits(&found.iter().collect::<Vec<_>>());
+}
+
+fn remove_clean_hits(hits: &[&CleanHit]) {
+    let mut removed = Vec::new();
+    for &h in hits {
+        let result = if h.path.is_dir() {
+            std::fs::remove_dir_all(&h.path)
+        } else {
+            std::fs::remove_file(&h.path)
+        };
+        match result {
+            Ok(_) => removed.push(h),
+            Err(e) => eprintln!("  {} {}: {}", "✗".red(), h.path.display(), e),
+        }
+    }
+    print_clean_summary("Reclaimed per rule:", &removed);
+    let reclaimed: u64 = removed.iter().map(|h| h.size).sum();
+    println!("\n{} Cleaned {} item(s), reclaimed {}.",
+        "✓".green().bold(), removed.len(), human_size(reclaimed).green().bold());
+}
+
+/// Group hits by the project they belong to (nearest directory holding a marker file or .git)
+fn clean_projects<'a>(base: &Path, presets: &[CleanPreset], hits: &'a [CleanHit]) -> Vec<(PathBuf, Vec<&'a CleanHit>)> {
+    use std::collections::HashMap;
+
+    let is_root = |dir: &Path| dir.join(".git").exists()
+        || presets.iter().flat_map(|p| &p.markers).any(|m| dir.join(m).is_file());
+    let mut cache: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut groups: HashMap<PathBuf, Vec<&CleanHit>> = HashMap::new();
+    for h in hits {
+        let parent = h.path.parent().unwrap_or(base);
+        let root = cache.entry(parent.to_path_buf()).or_insert_with(|| {
+            parent.ancestors()
+                .take_while(|a| a.starts_with(base))
+                .find(|a| is_root(a))
+                .unwrap_or(base)
+                .to_path_buf()
+        });
+        groups.entry(root.clone()).or_default().push(h);
+    }
+
+    let mut projects: Vec<_> = groups.into_iter().collect();
+    projects.sort_by(|a, b| {
+        let size = |hits: &[&CleanHit]| hits.iter().map(|h| h.size).sum::<u64>();
+        size(&b.1).cmp(&size(&a.1)).then_with(|| a.0.cmp(&b.0))
+    });
+    projects
+}
+
+fn cmd_clean_scan(base: &Path, presets: &[CleanPreset], found: &[CleanHit], yes: bool) {
+    let projects = clean_projects(base, presets, found);
+    let label = |root: &Path| match root.strip_prefix(base) {
+        Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+        Ok(rel) => rel.display().to_string(),
+        Err(_) => root.display().to_string(),
+    };
+    let rows: Vec<(String, usize, u64)> = projects.iter()
+        .map(|(root, hits)| (label(root), hits.len(), hits.iter().map(|h| h.size).sum()))
+        .collect();
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).clamp(7, 60);
+
+    println!("\n{} {} project(s) under {}\n", "Found".yellow().bold(), rows.len(), base.display());
+    println!("  {:<width$}  {:<8} {}", "PROJECT".bold().underline(), "ITEMS".bold().underline(), "RECLAIMABLE".bold().underline());
+    for (name, n, size) in &rows {
+        println!("  {:<width$}  {:<8} {}", name.cyan(), n, human_size(*size).yellow());
+    }
+    let total: u64 = rows.iter().map(|r| r.2).sum();
+    println!("  {:<width$}  {:<8} {}", "TOTAL".bold(), found.len().to_string().bold(), human_size(total).yellow().bold());
+
+    let picked: Vec<usize> = if yes {
+        (0..rows.len()).collect()
+    } else {
+        let items: Vec<String> = rows.iter()
+            .map(|(name, _, size)| format!("{:<width$}  {:>10}", name, human_size(*size)))
+            .collect();
+        println!();
+        dialoguer::MultiSelect::new()
+            .with_prompt("Select projects to clean (space to toggle, enter to confirm)")
+            .items(&items)
+            .interact()
+            .unwrap_or_else(|e| {
+                eprintln!("{} {} (use -y to clean every project)", "✗".red(), e);
+                std::process::exit(1);
+            })
+    };
+    if picked.is_empty() {
+        println!("{}", "Nothing selected.".dimmed());
+        return;
+    }
+
+    let hits: Vec<&CleanHit> = picked.iter().flat_map(|&i| projects[i].1.iter().copied()).collect();
+    remove_clean_hits(&hits);
+}
+
+// ─────────────────────────────────────────────────────────────
+//  ZIP
+// ─────────────────────────────────────────────────────────────
+/// Extensions that are already compressed — deflating them again only burns CPU
+const STORE_EXTS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "mp3", "mp4", "m4a", "mkv", "mov", "avi", "webm",
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "jar", "apk", "pdf", "woff2",
+];
+
+/// Zip `source` into `output`; returns the SHA-256 of every file entry as (entry name, hex digest)
+fn cmd_zip(source: &Path, output: Option<PathBuf>, opts: &ZipArgs) -> Vec<(String, String)> {
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+
+    let source = source.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Source not found: {}", "✗".red(), source.display());
+        std::process::exit(1);
+    });
+    let out = output.unwrap_or_else(|| {
+        PathBuf::from(format!("{}.zip", source.file_name().unwrap_or_default().to_string_lossy()))
+    });
+
+    // ask before creating the archive, so a bad password leaves nothing behind
+    let password = opts.encrypt.then(|| zip_password(opts.password_file.as_deref(), true));
+    let file = std::fs::File::create(&out).expect("Cannot create zip file");
+    let out_abs = out.canonicalize().unwrap_or(out.clone());
+    let mut zip = zip::ZipWriter::new(file);
+    let mut base = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .compression_level(opts.level.map(i64::from));
+    if let Some(pw) = &password {
+        base = base.with_aes_encryption(zip::AesMode::Aes256, pw);
+    }
+
+    let mut manifest = Vec::new();
+    let root = source.parent().unwrap_or(Path::new("/"));
+    for path in zip_inputs(&source, opts) {
+        if path == out_abs { continue; }
+        let rel = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        let mut fo = base.last_modified_time(zip_time(&path, opts.reproducible));
+        if path.is_dir() {
+            zip.add_directory(rel, fo).unwrap();
+            continue;
+        }
+        let ext = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+        if opts.store && STORE_EXTS.contains(&ext.as_str()) {
+            // stored entries reject any compression level
+            fo = fo.compression_method(zip::CompressionMethod::Stored).compression_level(None);
+        }
+        let Ok(data) = std::fs::read(&path) else {
+            eprintln!("  {} cannot read {}", "✗".red(), path.display());
+            continue;
+        };
+        if let Err(e) = zip.start_file(rel.as_str(), fo).and_then(|_| Ok(zip.write_all(&data)?)) {
+            eprintln!("{} Cannot add {} to {}: {}", "✗".red(), rel, out.display(), e);
+            std::process::exit(1);
+        }
+        manifest.push((rel, format!("{:x}", Sha256::digest(&data))));
+    }
+    zip.finish().unwrap();
+
+    let size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+    let lock = if password.is_some() { "  🔒 AES-256" } else { "" };
+    println!("{} Zipped {} file(s) → {}  ({}){}",
+        "✓".green().bold(), manifest.len(), out.display().to_string().cyan(),
+        human_size(size).yellow(), lock.dimmed());
+    manifest
+}
+
+/// Every file/dir under `source` (itself included) that survives the
+/// exclude / .gitignore filters, sorted by path so archives are stable.
+fn zip_inputs(source: &Path, opts: &ZipArgs) -> Vec<PathBuf> {
+    if source.is_file() {
+        return vec![source.to_path_buf()];
+    }
+
+    let mut excludes = ignore::gitignore::GitignoreBuilder::new(source);
+    for pat in &opts.exclude {
+        if let Err(e) = excludes.add_line(None, pat) {
+            eprintln!("{} Bad exclude pattern '{}': {}", "✗".red(), pat, e);
+            std::process::exit(1);
+        }
+    }
+    let excludes = excludes.build().unwrap_or_else(|_| ignore::gitignore::Gitignore::empty());
+    let skip_git = opts.gitignore;
+
+    let mut paths: Vec<PathBuf> = ignore::WalkBuilder::new(source)
+        .hidden(false)
+        .ignore(false)
+        .parents(opts.gitignore)
+        .git_ignore(opts.gitignore)
+        .git_global(opts.gitignore)
+        .git_exclude(opts.gitignore)
+        .require_git(false)
+        .filter_entry(move |e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            if skip_git && is_dir && e.file_name() == ".git" { return false; }
+            !excludes.matched(e.path(), is_dir).is_ignore()
+        })
+        .build()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn zip_time(path: &Path, reproducible: bool) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+    if reproducible { return zip::DateTime::default(); }
+    let Ok(modified) = path.metadata().and_then(|m| m.modified()) else {
+        return zip::DateTime::default();
+    };
+    let t: chrono::DateTime<chrono::Local> = modified.into();
+    zip::DateTime::from_date_and_time(
+        t.year() as u16, t.month() as u8, t.day() as u8,
+        t.hour() as u8, t.minute() as u8, t.second() as u8,
+    ).unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────
+//  UNZIP
+// ─────────────────────────────────────────────────────────────
+fn cmd_unzip(archive: &Path, destination: &Path, password_file: Option<&Path>) {
+    let mut zip = open_zip(archive);
+    std::fs::create_dir_all(destination).unwrap();
+    let total = zip.len();
+    let encrypted = (0..total).any(|i| zip.by_index_raw(i).is_ok_and(|f| f.encrypted()));
+
+    if !encrypted {
+        zip.extract(destination).expect("Extraction failed");
+    } else {
+        let password = zip_password(password_file, false);
+        for i in 0..total {
+            let mut f = match zip.by_index_decrypt(i, password.as_bytes()) {
+                Ok(f) => f,
+                Err(zip::result::ZipError::InvalidPassword) => {
+                    eprintln!("{} Wrong password.", "✗".red());
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{} Extraction failed: {}", "✗".red(), e);
+                    std::process::exit(1);
+                }
+            };
+            // enclosed_name() rejects absolute paths and `..` escapes
+            let Some(rel) = f.enclosed_name() else { continue };
+            let out = destination.join(rel);
+            if f.is_dir() {
+                std::fs::create_dir_all(&out).ok();
+                continue;
+            }
+            if let Some(parent) = out.parent() {
+                std::fs::create_dir_all(parent).ok();
+            }
+            let written = std::fs::File::create(&out).and_then(|mut w| std::io::copy(&mut f, &mut w));
+            if let Err(e) = written {
+                eprintln!("{} {}: {}", "✗".red(), out.display(), e);
+                std::process::exit(1);
+            }
+            #[cfg(unix)]
+            if let Some(mode) = f.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&out, std::fs::Permissions::from_mode(mode & 0o7777)).ok();
+            }
+        }
+    }
+    println!("{} Extracted {} files → {}",
+        "✓".green().bold(), total, destination.display().to_string().cyan());
+}
+
+// ─────────────────────────────────────────────────────────────
+//  ARCHIVE INSPECTION  — ls-archive / cat-archive
+// ─────────────────────────────────────────────────────────────
+#[derive(Clone, Copy, PartialEq)]
+enum ArchiveKind { Zip, Tar, TarGz }
+
+fn archive_kind(path: &Path) -> ArchiveKind {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        ArchiveKind::TarGz
+    } else if name.ends_with(".tar") {
+        ArchiveKind::Tar
+    } else {
+        ArchiveKind::Zip
+    }
+}
+
+struct ArchiveEntry {
+    path: String,
+    is_dir: bool,
+    size: u64,
+    compressed: Option<u64>,
+    mode: Option<u32>,
+    modified: Option<String>,
+}
+
+fn open_tar(archive: &Path, kind: ArchiveKind) -> tar::Archive<Box<dyn std::io::Read>> {
+    let file = std::fs::File::open(archive).unwrap_or_else(|e| {
+        eprintln!("{} Cannot open {}: {}", "✗".red(), archive.display(), e);
+        std::process::exit(1);
+    });
+    let reader: Box<dyn std::io::Read> = if kind == ArchiveKind::TarGz {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    tar::Archive::new(reader)
+}
+
+fn open_zip(archive: &Path) -> zip::ZipArchive<std::fs::File> {
+    let file = std::fs::File::open(archive).unwrap_or_else(|e| {
+        eprintln!("{} Cannot open {}: {}", "✗".red(), archive.display(), e);
+        std::process::exit(1);
+    });
+    zip::ZipArchive::new(file).unwrap_or_else(|e| {
+        eprintln!("{} Not a valid zip: {}", "✗".red(), e);
+        std::process::exit(1);
+    })
+}
+
+fn read_archive_entries(archive: &Path) -> Vec<ArchiveEntry> {
+    let kind = archive_kind(archive);
+    let mut out = Vec::new();
+    if kind == ArchiveKind::Zip {
+        let mut zip = open_zip(archive);
+        for i in 0..zip.len() {
+            let Ok(f) = zip.by_index_raw(i) else { continue };
+            let modified = f.last_modified().map(|t| format!("{:04}-{:02}-{:02} {:02}:{:02}",
+                t.year(), t.month(), t.day(), t.hour(), t.minute()));
+            out.push(ArchiveEntry {
+                path: f.name().trim_end_matches('/').to_string(),
+                is_dir: f.is_dir(),
+                size: f.size(),
+                compressed: Some(f.compressed_size()),
+                mode: f.unix_mode(),
+                modified,
+            });
+        }
+    } else {
+        let mut tar = open_tar(archive, kind);
+        let entries = tar.entries().unwrap_or_else(|e| {
+            eprintln!("{} Not a valid tar: {}", "✗".red(), e);
+            std::process::exit(1);
+        });
+        for entry in entries.filter_map(|e| e.ok()) {
+            let header = entry.header();
+            let path = entry.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+            // `tar -C dir -czf x.tgz .` stores "./" and "./name"; list them like cat-archive sees them
+            let path = path.trim_start_matches("./").trim_end_matches('/');
+            if path.is_empty() || path == "." { continue; }
+            let modified = header.mtime().ok()
+                .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+            out.push(ArchiveEntry {
+                path: path.to_string(),
+                is_dir: header.entry_type().is_dir(),
+                size: header.size().unwrap_or(0),
+                compressed: None,
+                mode: header.mode().ok(),
+                modified,
+            });
+        }
+    }
+    out
+}
+
+#[derive(Default)]
+struct ArchiveNode {
+    entry: Option<ArchiveEntry>,
+    children: std::collections::BTreeMap<String, ArchiveNode>,
+}
+
+fn cmd_ls_archive(archive: &Path, depth: usize) {
+    let entries = read_archive_entries(archive);
+
+    let (mut files, mut total, mut packed) = (0usize, 0u64, 0u64);
+    let mut root = ArchiveNode::default();
+    for e in entries {
+        if !e.is_dir {
+            files += 1;
+            total += e.size;
+            packed += e.compressed.unwrap_or(e.size);
+        }
+        let mut node = &mut root;
+        for part in e.path.split('/').filter(|p| !p.is_empty()) {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+        node.entry = Some(e);
+    }
+
+    println!("{}", archive.display().to_string().cyan().bold());
+    print_archive_tree(&root, "", depth, 0);
+
+    let ratio = if archive_kind(archive) == ArchiveKind::Zip {
+        format!("  → {} packed ({})", human_size(packed), savings(total, packed))
+    } else {
+        String::new()
+    };
+    println!("\n{} {} file(s), {}{}",
+        "✓".green().bold(), files, human_size(total).yellow(), ratio.dimmed());
+}
+
+fn print_archive_tree(node: &ArchiveNode, prefix: &str, max_depth: usize, current: usize) {
+    if current >= max_depth { return; }
+
+    let is_dir = |n: &ArchiveNode| !n.children.is_empty() || n.entry.as_ref().is_some_and(|e| e.is_dir);
+    let mut children: Vec<_> = node.children.iter().collect();
+    children.sort_by_key(|(name, n)| (!is_dir(n), name.to_lowercase()));
+
+    let count = children.len();
+    for (i, (name, child)) in children.into_iter().enumerate() {
+        let is_last = i + 1 == count;
+        let connector = if is_last { "└── " } else { "├── " };
+        let extension = if is_last { "    " } else { "│   " };
+
+        if is_dir(child) {
+            println!("{}{}{}", prefix, connector, name.blue().bold());
+            print_archive_tree(child, &format!("{prefix}{extension}"), max_depth, current + 1);
+        } else if let Some(e) = &child.entry {
+            let mut meta = human_size(e.size);
+            if let Some(c) = e.compressed {
+                meta.push_str(&format!(" → {} ({})", human_size(c), savings(e.size, c)));
+            }
+            if let Some(m) = e.mode {
+                meta.push_str(&format!("  {}", fmt_mode(m)));
+            }
+            if let Some(t) = &e.modified {
+                meta.push_str(&format!("  {t}"));
+            }
+            println!("{}{}{} {}", prefix, connector, name.green(), meta.dimmed());
+        }
+    }
+}
+
+fn savings(size: u64, compressed: u64) -> String {
+    if size == 0 { return "0%".into(); }
+    let pct = ((size as f64 - compressed as f64) * 100.0 / size as f64).round() as i64;
+    format!("{pct}%")
+}
+
+fn fmt_mode(mode: u32) -> String {
+    const BITS: &[(u32, char)] = &[
+        (0o400, 'r'), (0o200, 'w'), (0o100, 'x'),
+        (0o040, 'r'), (0o020, 'w'), (0o010, 'x'),
+        (0o004, 'r'), (0o002, 'w'), (0o001, 'x'),
+    ];
+    BITS.iter().map(|&(bit, c)| if mode & bit != 0 { c } else { '-' }).collect()
+}
+
+fn cmd_cat_archive(archive: &Path, entry: &str) {
+    use std::io::Write;
+    let want = entry.trim_start_matches("./").trim_end_matches('/');
+    let kind = archive_kind(archive);
+    let mut stdout = std::io::stdout().lock();
+
+    let found = if kind == ArchiveKind::Zip {
+        let mut zip = open_zip(archive);
+        let encrypted = zip.index_for_name(want)
+            .is_some_and(|i| zip.by_index_raw(i).is_ok_and(|f| f.encrypted()));
+        let file = if encrypted {
+            zip.by_name_decrypt(want, zip_password(None, false).as_bytes())
+        } else {
+            zip.by_name(want)
+        };
+        match file {
+            Ok(mut f) if f.is_file() => std::io::copy(&mut f, &mut stdout).is_ok(),
+            _ => false,
+        }
+    } else {
+        let mut tar = open_tar(archive, kind);
+        let mut hit = false;
+        if let Ok(entries) = tar.entries() {
+            for mut e in entries.filter_map(|e| e.ok()) {
+                let path = e.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+                if path.trim_start_matches("./") == want && e.header().entry_type().is_file() {
+                    hit = std::io::copy(&mut e, &mut stdout).is_ok();
+                    break;
+                }
+            }
+        }
+        hit
+    };
+    stdout.flush().ok();
+
+    if !found {
+        eprintln!("{} No file '{}' in {}", "✗".red(), want, archive.display());
+        std::process::exit(1);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  RENAME
+// ─────────────────────────────────────────────────────────────
+#[derive(Args, Clone, Default)]
+struct RenameOpts {
+    /// Preview only, don't rename
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Treat PATTERN as a regex; $1 / ${name} in REPLACEMENT insert capture groups
+    #[arg(short, long)]
+    regex: bool,
+    /// New name for every selected file, e.g. "{stem}_{n:03}.{ext}" or "{date:%Y%m%d}_{name}"
+    #[arg(short = 'T', long)]
+    template: Option<String>,
+    /// Lower-case the whole new name, extension included
+    #[arg(long, conflicts_with_all = ["snake", "kebab"])]
+    lower: bool,
+    /// snake_case the new name (extension kept)
+    #[arg(long, conflicts_with = "kebab")]
+    snake: bool,
+    /// kebab-case the new name (extension kept)
+    #[arg(long)]
+    kebab: bool,
+    /// First value of {n}
+    #[arg(long, default_value_t = 1)]
+    start: usize,
+    /// Rename directories too (deepest first)
+    #[arg(short = 'D', long)]
+    dirs: bool,
+    /// Only rename files matching this glob (e.g. "*.jpg", "photos/**")
+    #[arg(short, long)]
+    glob: Option<String>,
+    /// Match the glob case-sensitively
+    #[arg(short = 's', long)]
+    case_sensitive: bool,
+    /// On a name clash, append _1, _2 … instead of refusing
+    #[arg(long)]
+    suffix: bool,
+    /// Edit the names in $EDITOR, one path per line
+    #[arg(short, long, conflicts_with_all = ["template", "lower", "snake", "kebab"])]
+    edit: bool,
+    /// Don't ask before applying the edited names
+    #[arg(short = 'y', long)]
+    yes: bool,
+}
+
+/// Values a rename template can pull from the original file
+struct RenameCtx {
+    name: String,
+    stem: String,
+    ext: String,
+    parent: String,
+    modified: Option<chrono::DateTime<chrono::Local>>,
+    n: usize,
+}
+
+impl RenameCtx {
+    fn new(path: &Path, is_dir: bool, n: usize) -> RenameCtx {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let (stem, ext) = split_ext(&name, is_dir);
+        RenameCtx {
+            stem: stem.to_string(),
+            ext: ext.to_string(),
+            parent: path.parent().and_then(|p| p.file_name()).map(|p| p.to_string_lossy().to_string()).unwrap_or_default(),
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok().map(Into::into),
+            name,
+            n,
+        }
+    }
+
+    /// Fill {stem} {ext} {name} {parent} {n} {n:03} {date} {date:%Y%m%d}; anything else is left alone
+    fn expand(&self, template: &str) -> String {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let Some(close) = rest[open..].find('}').map(|c| open + c) else {
+                rest = &rest[open..];
+                break;
+            };
+            let token = &rest[open + 1..close];
+            let (key, spec) = token.split_once(':').unwrap_or((token, ""));
+            let value = match key {
+                _ if out.ends_with('$') => None, // ${1} belongs to the regex
+                "name" => Some(self.name.clone()),
+                "stem" => Some(self.stem.clone()),
+                "ext" => Some(self.ext.clone()),
+                "parent" => Some(self.parent.clone()),
+                "n" => Some(match spec.parse::<usize>() {
+                    Ok(width) => format!("{:0width$}", self.n),
+                    Err(_) => self.n.to_string(),
+                }),
+                "date" => {
+                    let fmt = if spec.is_empty() { "%Y-%m-%d" } else { spec };
+                    let valid = chrono::format::StrftimeItems::new(fmt).all(|i| !matches!(i, chrono::format::Item::Error));
+                    self.modified.filter(|_| valid).map(|t| t.format(fmt).to_string())
+                }
+                _ => None,
+            };
+            match value {
+                // "{stem}.{ext}" on a file without an extension shouldn't leave a trailing dot
+                Some(v) if key == "ext" && v.is_empty() && out.ends_with('.') => { out.pop(); }
+                Some(v) => out.push_str(&v),
+                None => out.push_str(&rest[open..=close]),
+            }
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// ("archive.tar", "gz") — a leading dot (".bashrc") isn't an extension, and folders have none
+fn split_ext(name: &str, is_dir: bool) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 && !is_dir => (&name[..i], &name[i + 1..]),
+        _ => (name, ""),
+    }
+}
+
+/// Break "myHTTPServer v2.final" into ["my", "HTTP", "Server", "v2", "final"]
+fn name_words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() { words.push(std::mem::take(&mut word)); }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase() && prev.is_some_and(|p| {
+            p.is_lowercase() || p.is_ascii_digit()
+                || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+        });
+        if boundary && !word.is_empty() { words.push(std::mem::take(&mut word)); }
+        word.push(c);
+    }
+    if !word.is_empty() { words.push(word); }
+    words
+}
+
+fn apply_name_case(name: &str, is_dir: bool, opts: &RenameOpts) -> String {
+    if opts.lower { return name.to_lowercase(); }
+    let sep = match (opts.snake, opts.kebab) {
+        (true, _) => "_",
+        (_, true) => "-",
+        _ => return name.to_string(),
+    };
+    let (stem, ext) = split_ext(name, is_dir);
+    let dot = if name.starts_with('.') { "." } else { "" };
+    let stem = dot.to_string() + &name_words(stem).join(sep).to_lowercase();
+    if ext.is_empty() { stem } else { format!("{stem}.{ext}") }
+}
+
+/// One rename in a plan — only the last path component changes
+#[derive(Clone)]
+struct RenameStep {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+fn path_taken(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok()
+}
+
+/// True when both paths are the same file, e.g. "a.txt" → "A.txt" on a case-insensitive disk
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Targets that clash with each other or with files staying put. With `suffix` they are bumped
+/// to a free "name_1.ext" instead; whatever can't be resolved is returned as "from → to  (why)".
+fn resolve_rename_conflicts(plan: &mut [RenameStep], base: &Path, suffix: bool) -> Vec<String> {
+    let sources: std::collections::HashSet<PathBuf> = plan.iter().map(|s| s.from.clone()).collect();
+    let mut claimed: std::collections::HashMap<PathBuf, PathBuf> = std::collections::HashMap::new();
+    let mut problems = Vec::new();
+    for step in plan.iter_mut() {
+        let clash = |to: &Path, claimed: &std::collections::HashMap<PathBuf, PathBuf>| {
+            if let Some(other) = claimed.get(to) {
+                Some(format!("also the target of {}", other.strip_prefix(base).unwrap_or(other).display()))
+            } else if path_taken(to) && !sources.contains(to) && !same_file(&step.from, to) {
+                Some("already exists".to_string())
+            } else {
+                None
+            }
+        };
+        if let Some(why) = clash(&step.to, &claimed) {
+            if suffix {
+                let name = step.to.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let is_dir = std::fs::symlink_metadata(&step.from).is_ok_and(|m| m.is_dir());
+                let (stem, ext) = split_ext(&name, is_dir);
+                step.to = (1..)
+                    .map(|k| step.to.with_file_name(if ext.is_empty() { format!("{stem}_{k}") } else { format!("{stem}_{k}.{ext}") }))
+                    .find(|to| clash(to, &claimed).is_none())
+                    .unwrap_or_default();
+            } else {
+                let from = step.from.strip_prefix(base).unwrap_or(&step.from);
+                let to = step.to.file_name().unwrap_or_default().to_string_lossy();
+                problems.push(format!("{}  →  {}  ({why})", from.display(), to));
+                continue;
+            }
+        }
+        claimed.insert(step.to.clone(), step.from.clone());
+    }
+    problems
+}
+
+/// Turn a plan into actual moves: (from, to, plan index, final move?). Children go before their
+/// parents, a rename whose target is still occupied by another source waits for it to move, and
+/// cycles (a → b, b → a) go through a temporary name.
+fn order_renames(plan: &[RenameStep]) -> Vec<(PathBuf, PathBuf, usize, bool)> {
+    let by_from: std::collections::HashMap<&Path, usize> = plan.iter().enumerate().map(|(i, s)| (s.from.as_path(), i)).collect();
+    // occupant[i]: the step currently sitting on i's target, which has to move first
+    let occupant: Vec<Option<usize>> = plan.iter().enumerate()
+        .map(|(i, s)| by_from.get(s.to.as_path()).copied().filter(|&j| j != i))
+        .collect();
+    let mut waiting_on = vec![None; plan.len()];
+    for (i, o) in occupant.iter().enumerate() {
+        if let Some(j) = o { waiting_on[*j] = Some(i); }
+    }
+
+    let mut order: Vec<usize> = (0..plan.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(plan[i].from.components().count()));
+
+    let mut done = vec![false; plan.len()];
+    let mut moves = Vec::new();
+    for &i in &order {
+        if done[i] { continue; }
+        // walk back to the start of i's chain, or all the way round if it's a cycle
+        let mut head = i;
+        let mut cycle = false;
+        while let Some(p) = waiting_on[head] {
+            if p == i { cycle = true; break; }
+            head = p;
+        }
+        let start = if cycle { i } else { head };
+        let mut chain = vec![start];
+        while let Some(j) = occupant[*chain.last().unwrap_or(&start)] {
+            if j == start { break; }
+            chain.push(j);
+        }
+        for &j in &chain { done[j] = true; }
+
+        if cycle {
+            let from = &plan[start].from;
+            let name = from.file_name().unwrap_or_default().to_string_lossy();
+            let temp = (0..)
+                .map(|k| from.with_file_name(format!(".{name}.vasu-rename{}", if k == 0 { String::new() } else { format!("-{k}") })))
+                .find(|t| !path_taken(t))
+                .unwrap_or_default();
+            moves.push((from.clone(), temp.clone(), start, false));
+            for &j in chain[1..].iter().rev() {
+                moves.push((plan[j].from.clone(), plan[j].to.clone(), j, true));
+            }
+            moves.push((temp, plan[start].to.clone(), start, true));
+        } else {
+            for &j in chain.iter().rev() {
+                moves.push((plan[j].from.clone(), plan[j].to.clone(), j, true));
+            }
+        }
+    }
+    moves
+}
+
+/// Check, order and carry out a rename plan; exits non-zero on conflicts or failed renames
+fn run_rename_plan(mut plan: Vec<RenameStep>, base: &Path, dry_run: bool, suffix: bool) {
+    if plan.is_empty() {
+        println!("{}", "Nothing to rename.".yellow());
+        return;
+    }
+    let problems = resolve_rename_conflicts(&mut plan, base, suffix);
+    if !problems.is_empty() {
+        eprintln!("{} {} conflict(s), nothing renamed:", "✗".red(), problems.len());
+        for p in &problems { eprintln!("    {}", p); }
+        eprintln!("  {}", "Re-run with --suffix to number clashing names instead.".dimmed());
+        std::process::exit(1);
+    }
+
+    let tag = if dry_run { format!("  {}", "(dry-run)".dimmed()) } else { String::new() };
+    let mut failed = vec![false; plan.len()];
+    let mut count = 0usize;
+    for (from, to, i, last) in order_renames(&plan) {
+        if failed[i] { continue; }
+        let step = &plan[i];
+        let rel = step.from.strip_prefix(base).unwrap_or(&step.from).display().to_string();
+        let new_name = step.to.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !dry_run {
+            // the plan has made sure the target is free; if it isn't, something changed under us
+            let moved = if path_taken(&to) && !same_file(&from, &to) {
+                Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{} already exists", to.display())))
+            } else {
+                std::fs::rename(&from, &to)
+            };
+            if let Err(e) = moved {
+                failed[i] = true;
+                println!("  {}  →  {}", rel.yellow(), new_name.red());
+                eprintln!("    {} {}", "✗".red(), e);
+                if !last { continue; }
+                eprintln!("    {} left as {}", "!".yellow(), from.display());
+                continue;
+            }
+        }
+        if last {
+            println!("  {}  →  {}{}", rel.yellow(), new_name.green(), tag);
+            count += 1;
+        }
+    }
+
+    let verb = if dry_run { "Would rename" } else { "Renamed" };
+    println!("\n{} {verb} {count} item(s).", "✓".green().bold());
+    let failures = failed.iter().filter(|f| **f).count();
+    if failures > 0 {
+        eprintln!("{} {} rename(s) failed.", "✗".red(), failures);
+        std::process::exit(1);
+    }
+}
+
+/// Files (and with -D folders) under `directory` picked by the glob and pattern, sorted by name
+fn rename_entries(
+    pattern: Option<&str>,
+    re: Option<&regex::Regex>,
+    only: Option<&glob::Pattern>,
+    directory: &Path,
+    opts: &RenameOpts,
+) -> Vec<walkdir::DirEntry> {
+    walkdir::WalkDir::new(directory).min_depth(1).sort_by_file_name().into_iter()
+        .filter_map(|e| e.ok())
+        // A symlink to a file is renamed like a file (the link itself moves, not its target).
+        .filter(|e| e.path().is_file() || (opts.dirs && e.file_type().is_dir()))
+        .filter(|e| {
+            let rel = e.path().strip_prefix(directory).unwrap_or(e.path());
+            only.is_none_or(|g| g.matches(rel, e.file_type().is_dir()))
+        })
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy();
+            match (pattern, re) {
+                (None, _) => true,
+                (Some(_), Some(re)) => re.is_match(&name),
+                (Some(p), None) => name.contains(p),
+            }
+        })
+        .collect()
+}
+
+/// With -T, --edit or a case flag PATTERN and REPLACEMENT are optional, so a directory given
+/// last lands in one of them. Hand it back as the directory: with -T / --edit whatever follows
+/// the pattern, and otherwise a trailing path that names a folder (names can't hold a '/').
+fn rename_positionals(
+    mut pattern: Option<String>,
+    mut replacement: Option<String>,
+    directory: Option<PathBuf>,
+    opts: &RenameOpts,
+) -> (Option<String>, Option<String>, PathBuf) {
+    let fail = |msg: String| -> ! {
+        eprintln!("{} {}", "✗".red(), msg);
+        std::process::exit(2);
+    };
+    let no_replacement = opts.template.is_some() || opts.edit;
+    let looks_like_dir = |s: &str| {
+        (s.contains(['/', std::path::MAIN_SEPARATOR]) || s == "." || s == "..") && Path::new(s).is_dir()
+    };
+    let mut directory = directory;
+    if directory.is_none() {
+        let second = replacement.is_some();
+        let last = if second { &mut replacement } else { &mut pattern };
+        if last.as_deref().is_some_and(|s| looks_like_dir(s) || (no_replacement && second)) {
+            directory = last.take().map(PathBuf::from);
+        }
+    }
+    if no_replacement {
+        if let Some(r) = replacement {
+            fail(format!("Unexpected '{r}': --template / --edit take no REPLACEMENT"));
+        }
+    }
+    (pattern, replacement, directory.unwrap_or_else(|| PathBuf::from(".")))
+}
+
+fn cmd_rename(pattern: Option<&str>, replacement: Option<&str>, directory: &Path, opts: &RenameOpts) {
+    let only = opts.glob.as_deref().map(|g| glob::Pattern::new(g, opts.case_sensitive).unwrap_or_else(|e| {
+        eprintln!("{} {}", "✗".red(), e);
+        std::process::exit(1);
+    }));
+    let re = pattern.filter(|_| opts.regex).map(|p| regex::Regex::new(p).unwrap_or_else(|e| {
+        eprintln!("{} Invalid regex: {}", "✗".red(), e);
+        std::process::exit(1);
+    }));
+
+    let entries = rename_entries(pattern, re.as_ref(), only.as_ref(), directory, opts);
+    if opts.edit {
+        return cmd_rename_edit(entries, directory, opts);
+    }
+
+    // work out every new name first, in a stable order so {n} is predictable
+    let mut plan = Vec::new();
+    for (n, entry) in (opts.start..).zip(entries) {
+        let is_dir = entry.file_type().is_dir();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let ctx = RenameCtx::new(entry.path(), is_dir, n);
+        let renamed = match (&opts.template, replacement, &re) {
+            (Some(t), _, _) => ctx.expand(t),
+            (None, Some(r), Some(re)) => re.replace_all(&name, ctx.expand(r).as_str()).into_owned(),
+            (None, Some(r), None) => name.replace(pattern.unwrap_or_default(), &ctx.expand(r)),
+            (None, None, _) => name.clone(),
+        };
+        let renamed = apply_name_case(&renamed, is_dir, opts);
+        if renamed == name || renamed.is_empty() { continue; }
+        if renamed.contains(['/', std::path::MAIN_SEPARATOR]) {
+            eprintln!("{} {}  →  {}  contains a path separator, skipped", "!".yellow(), name, renamed);
+            continue;
+        }
+        let to = entry.path().with_file_name(&renamed);
+        plan.push(RenameStep { from: entry.into_path(), to });
+    }
+    run_rename_plan(plan, directory, opts.dry_run, opts.suffix);
+}
+
+/// vidir-style: list the paths in a temp file, open $EDITOR, then rename whatever lines changed
+fn cmd_rename_edit(entries: Vec<walkdir::DirEntry>, directory: &Path, opts: &RenameOpts) {
+    let (entries, skipped): (Vec<_>, Vec<_>) =
+        entries.into_iter().partition(|e| !e.file_name().to_string_lossy().contains('\n'));
+    for e in &skipped {
+        eprintln!("{} {:?} has a newline in its name, left out", "!".yellow(), e.path());
+    }
+    if entries.is_empty() {
+        println!("{}", "Nothing to rename.".yellow());
+        return;
+    }
+    let rels: Vec<PathBuf> = entries.iter()
+        .map(|e| e.path().strip_prefix(directory).unwrap_or(e.path()).to_path_buf())
+        .collect();
+    let mut text: String = rels.iter().map(|r| format!("{}\n", r.display())).collect();
+
+    let tmp = create_temp_file("vasu-rename").unwrap_or_else(|e| {
+        eprintln!("{} Cannot create a temp file: {}", "✗".red(), e);
+        std::process::exit(1);
+    });
+    let plan = loop {
+        let edited = match edit_in_editor(&tmp.path, &text) {
+            Ok(edited) => edited,
+            Err(e) => {
+                eprintln!("{} {}", "✗".red(), e);
+                break None;
+            }
+        };
+        match parse_rename_edit(&entries, &rels, &edited, directory, opts.suffix) {
+            Ok(plan) => break Some(plan),
+            Err(problems) => {
+                eprintln!("{} The edited list can't be applied:", "✗".red());
+                for p in &problems { eprintln!("    {}", p); }
+                text = edited;
+                if !confirm("\nEdit again?") { break None; }
+            }
+        }
+    };
+    // gone before we exit either way
+    drop(tmp);
+    let Some(plan) = plan else { std::process::exit(1) };
+
+    if plan.is_empty() {
+        println!("{}", "Nothing to rename.".yellow());
+        return;
+    }
+    if opts.dry_run || !opts.yes {
+        run_rename_plan(plan.clone(), directory, true, opts.suffix);
+        if opts.dry_run || !confirm("\nProceed?") { return; }
+        println!();
+    }
+    run_rename_plan(plan, directory, false, opts.suffix);
+}
+
+/// Match the edited lines back to the listed paths; every line must still be there, in order,
+/// and only the last component may change
+fn parse_rename_edit(
+    entries: &[walkdir::DirEntry],
+    rels: &[PathBuf],
+    edited: &str,
+    directory: &Path,
+    suffix: bool,
+) -> Result<Vec<RenameStep>, Vec<String>> {
+    let edited = edited.trim_end_matches(['\n', '\r']);
+    let lines: Vec<&str> = if edited.is_empty() {
+        Vec::new()
+    } else {
+        edited.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).collect()
+    };
+    if lines.len() != rels.len() {
+        return Err(vec![format!(
+            "expected {} line(s), got {} — keep one line per path, in the same order",
+            rels.len(), lines.len()
+        )]);
+    }
+
+    let mut plan = Vec::new();
+    let mut problems = Vec::new();
+    for (k, ((entry, rel), line)) in entries.iter().zip(rels).zip(&lines).enumerate() {
+        let old = rel.display().to_string();
+        if *line == old { continue; }
+        let new = Path::new(line);
+        match new.file_name().filter(|_| !line.ends_with('/')) {
+            None => problems.push(format!("line {}: {:?} isn't a valid name", k + 1, line)),
+            Some(_) if new.parent() != rel.parent() =>
+                problems.push(format!("line {}: only the name can change, not the folder ({} → {})", k + 1, old, line)),
+            Some(name) => plan.push(RenameStep { from: entry.path().to_path_buf(), to: entry.path().with_file_name(name) }),
+        }
+    }
+    // duplicates and clashes with files that aren't in the list
+    if problems.is_empty() {
+        problems = resolve_rename_conflicts(&mut plan.clone(), directory, suffix);
+    }
+    if problems.is_empty() { Ok(plan) } else { Err(problems) }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  COUNT
+// ─────────────────────────────────────────────────────────────
+fn cmd_count(directory: &Path, ext_filter: Vec<String>) {
+    use std::collections::HashMap;
+    let filter: Vec<String> = ext_filter.iter().map(|e| {
+        if e.starts_with('.') { e.clone() } else { format!(".{e}") }
+    }).collect();
+
+    let mut by_ext: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for entry in walkdir::WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() { continue; }
+        let path = entry.path();
+        let ext = path.extension()
+            .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+            .unwrap_or_else(|| "(no ext)".into());
+
+        if !filter.is_empty() && !filter.contains(&ext) { continue; }
+
+        let lines = std::fs::read_to_string(path)
+            .map(|s| s.lines().count())
+            .unwrap_or(0);
+
+        let entry = by_ext.entry(ext).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += lines;
+    }
+
+    if by_ext.is_empty() {
+        println!("{}", "No files found.".yellow());
+        return;
+    }
+
+    let mut rows: Vec<_> = by_ext.iter().collect();
+    rows.sort_by_key(|r| std::cmp::Reverse(r.1.0));
+
+    println!("\n  {:<14} {:<10} {}", "EXTENSION".bold(), "FILES".bold(), "LINES".bold());
+    let (mut tf, mut tl) = (0usize, 0usize);
+    for (ext, (files, lines)) in &rows {
+        println!("  {:<14} {:<10} {:>10}", ext.cyan(), files.to_string().yellow(), format!("{lines:>10}").white());
+        tf += files; tl += lines;
+    }
+    println!("  {:<14} {:<10} {:>10}", "TOTAL".bold(), tf.to_string().bold(), format!("{tl:>10}").bold());
+    println!();
+}
+
+// ─────────────────────────────────────────────────────────────
+//  HASH
+// ─────────────────────────────────────────────────────────────
+fn cmd_hash(file: &Path) {
+    use sha2::{Digest, Sha256};
+    if !file.is_file() {
+        eprintln!("{} Not a file: {}", "✗".red(), file.display());
+        std::process::exit(1);
+    }
+    let data = std::fs::read(file).expect("Cannot read file");
+    let md5_hash = format!("{:x}", md5::compute(&data));
+    let sha256_hash = format!("{:x}", Sha256::digest(&data));
+    let size = human_size(data.len() as u64);
+
+    println!("\n  {} {}", "File:  ".bold(), file.display().to_string().cyan());
+    println!("  {} {}", "Size:  ".bold(), size.yellow());
+    println!("  {} {}", "MD5:   ".bold(), md5_hash.green());
+    println!("  {} {}", "SHA256:".bold(), sha256_hash.green());
+    println!();
+}
+
+// ─────────────────────────────────────────────────────────────
+//  BACKUP
+// ─────────────────────────────────────────────────────────────
+fn cmd_backup(source: &Path, dest: &Path, opts: &ZipArgs, incremental: bool) {
+    use chrono::Local;
+    if incremental {
+        backup_incremental(source, dest, opts);
+        return;
+    }
+    let stamp = Local::now().format("%Y%m%d_%H%M%S");
+    let abs = source.canonicalize().unwrap_or(source.to_path_buf());
+    let name = abs.file_name().unwrap_or(std::ffi::OsStr::new("backup"))
+        .to_string_lossy();
+    std::fs::create_dir_all(dest).unwrap();
+    let out = dest.join(format!("{name}_{stamp}.zip"));
+    let manifest = cmd_zip(source, Some(out.clone()), opts);
+    // `sha256sum -c` skips the leading comment line
+    let filter = serde_json::to_string(&BackupFilter::from_opts(opts)).unwrap_or_default();
+    let sums: String = std::iter::once(format!("{MANIFEST_FILTER}{filter}\n"))
+        .chain(manifest.iter().map(|(name, hash)| format!("{hash}  {name}\n")))
+        .collect();
+    if let Err(e) = std::fs::write(manifest_path(&out), sums) {
+        eprintln!("{} Cannot write checksum manifest: {}", "!".yellow(), e);
+    }
+    let size = std::fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+    println!("{} Backup saved → {}  ({})",
+        "✓".green().bold(), out.display().to_string().cyan(), human_size(size).yellow());
+}
+
+// ─────────────────────────────────────────────────────────────
+//  BACKUP REPOSITORY  — incremental, content-addressed
+//
+//  <repo>/chunks/ab/abcdef…   zlib-compressed chunk, named by SHA-256 of its plain bytes
+//  <repo>/snapshots/<id>.json one manifest per backup (file list + chunk hashes)
+// ─────────────────────────────────────────────────────────────
+const CHUNK_MIN: usize = 256 * 1024;
+const CHUNK_MAX: usize = 4 * 1024 * 1024;
+/// Boundary when the low 20 bits of the rolling hash are zero → ~1 MiB average chunks
+const CHUNK_MASK: u64 = (1 << 20) - 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    id: String,
+    source: PathBuf,
+    created: String,
+    files: Vec<SnapshotFile>,
+    /// What was left out, so `backup verify -s` doesn't report excluded files as new
+    #[serde(default)]
+    filter: BackupFilter,
+}
+
+/// The exclude / .gitignore settings a backup was taken with
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+struct BackupFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
+    #[serde(default)]
+    gitignore: bool,
+}
+
+impl BackupFilter {
+    fn from_opts(opts: &ZipArgs) -> Self {
+        BackupFilter { exclude: opts.exclude.clone(), gitignore: opts.gitignore }
+    }
+
+    fn zip_args(&self) -> ZipArgs {
+        ZipArgs { exclude: self.exclude.clone(), gitignore: self.gitignore, ..ZipArgs::default() }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+struct SnapshotFile {
+    path: String,
+    #[serde(default)]
+    dir: bool,
+    size: u64,
+    mode: u32,
+    mtime: i64,
+    chunks: Vec<String>,
+    /// Whole-file SHA-256 (absent in manifests written before `backup verify` existed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+impl Snapshot {
+    fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+fn is_backup_repo(path: &Path) -> bool {
+    path.join("snapshots").is_dir() && path.join("chunks").is_dir()
+}
+
+/// Every snapshot manifest, plus "file: error" for each one that can't be read or parsed
+fn read_snapshots(repo: &Path) -> (Vec<Snapshot>, Vec<String>) {
+    let (mut snaps, mut broken) = (Vec::new(), Vec::new());
+    let entries = std::fs::read_dir(repo.join("snapshots")).into_iter().flatten().filter_map(|e| e.ok());
+    for path in entries.map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "json")) {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str::<Snapshot>(&text).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(snap) => snaps.push(snap),
+            Err(e) => broken.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    snaps.sort_by(|a, b| a.id.cmp(&b.id));
+    (snaps, broken)
+}
+
+fn load_snapshots(repo: &Path) -> Vec<Snapshot> {
+    let (snaps, broken) = read_snapshots(repo);
+    for b in &broken {
+        eprintln!("{} Unreadable snapshot {}", "!".yellow(), b);
+    }
+    snaps
+}
+
+/// Held while a backup or prune writes to a repository, so garbage collection
+/// never deletes chunks a running backup is about to reference
+struct RepoLock {
+    path: PathBuf,
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+fn lock_repo(repo: &Path) -> RepoLock {
+    use std::io::Write;
+    let path = repo.join("lock");
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(mut f) => {
+            writeln!(f, "{}", std::process::id()).ok();
+            RepoLock { path }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            eprintln!("{} {} is in use by another backup or prune. If none is running, delete {}",
+                "✗".red(), repo.display(), path.display());
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{} Cannot lock {}: {}", "✗".red(), repo.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn chunk_path(repo: &Path, hash: &str) -> PathBuf {
+    repo.join("chunks").join(&hash[..2]).join(hash)
+}
+
+/// Gear table for the content-defined chunker (splitmix64, fixed seed so
+/// boundaries stay stable across runs and machines).
+fn gear_table() -> &'static [u64; 256] {
+    static GEAR: std::sync::OnceLock<[u64; 256]> = std::sync::OnceLock::new();
+    GEAR.get_or_init(|| {
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+        std::array::from_fn(|_| {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        })
+    })
+}
+
+/// Write one chunk into the repository unless it is already there.
+/// Returns the chunk hash and the number of bytes newly written to disk.
+fn put_chunk(repo: &Path, data: &[u8], level: u32) -> std::io::Result<(String, u64)> {
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+
+    let hash = format!("{:x}", Sha256::digest(data));
+    let target = chunk_path(repo, &hash);
+    if target.exists() {
+        return Ok((hash, 0));
+    }
+    std::fs::create_dir_all(target.parent().unwrap())?;
+    let mut enc = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::new(level));
+    enc.write_all(data)?;
+    let packed = enc.finish()?;
+    // write-then-rename so an interrupted backup never leaves a half chunk behind
+    let tmp = target.with_extension("tmp");
+    std::fs::write(&tmp, &packed)?;
+    std::fs::rename(&tmp, &target)?;
+    Ok((hash, packed.len() as u64))
+}
+
+fn get_chunk(repo: &Path, hash: &str) -> std::io::Result<Vec<u8>> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let packed = std::fs::read(chunk_path(repo, hash))?;
+    let mut data = Vec::new();
+    flate2::read::ZlibDecoder::new(&packed[..]).read_to_end(&mut data)?;
+    if format!("{:x}", Sha256::digest(&data)) != hash {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("chunk {hash} is corrupt")));
+    }
+    Ok(data)
+}
+
+/// Split a file into content-defined chunks and store them.
+/// Returns the chunk list, the whole-file SHA-256 and the bytes newly written to the repository.
+fn store_file(repo: &Path, path: &Path, level: u32) -> std::io::Result<(Vec<String>, String, u64)> {
+    use sha2::{Digest, Sha256};
+    use std::io::BufRead;
+
+    let gear = gear_table();
+    let mut reader = std::io::BufReader::with_capacity(1 << 20, std::fs::File::open(path)?);
+    let mut chunk: Vec<u8> = Vec::with_capacity(CHUNK_MAX);
+    let (mut hashes, mut written, mut h) = (Vec::new(), 0u64, 0u64);
+    let mut whole = Sha256::new();
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() { break; }
+        let n = buf.len();
+        whole.update(buf);
+        for &b in buf {
+            chunk.push(b);
+            h = (h << 1).wrapping_add(gear[b as usize]);
+            if (chunk.len() >= CHUNK_MIN && h & CHUNK_MASK == 0) || chunk.len() >= CHUNK_MAX {
+                let (hash, w) = put_chunk(repo, &chunk, level)?;
+                hashes.push(hash);
+                written += w;
+                chunk.clear();
+                h = 0;
+            }
+        }
+        reader.consume(n);
+    }
+    if !chunk.is_empty() {
+        let (hash, w) = put_chunk(repo, &chunk, level)?;
+        hashes.push(hash);
+        written += w;
+    }
+    Ok((hashes, format!("{:x}", whole.finalize()), written))
+}
+
+fn file_mode_mtime(meta: &std::fs::Metadata) -> (u32, i64) {
+    #[cfg(unix)]
+    let mode = { use std::os::unix::fs::PermissionsExt; meta.permissions().mode() & 0o7777 };
+    #[cfg(not(unix))]
+    let mode = if meta.permissions().readonly() { 0o444 } else { 0o644 };
+    let mtime = meta.modified().ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    (mode, mtime)
+}
+
+fn backup_incremental(source: &Path, repo: &Path, opts: &ZipArgs) {
+    let source = source.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Source not found: {}", "✗".red(), source.display());
+        std::process::exit(1);
+    });
+    for dir in ["chunks", "snapshots"] {
+        std::fs::create_dir_all(repo.join(dir)).unwrap_or_else(|e| {
+            eprintln!("{} Cannot create repository at {}: {}", "✗".red(), repo.display(), e);
+            std::process::exit(1);
+        });
+    }
+    let lock = lock_repo(repo);
+    let repo_abs = repo.canonicalize().unwrap_or(repo.to_path_buf());
+    let level = opts.level.unwrap_or(6) as u32;
+
+    // unchanged files (same size + mtime as the last snapshot of this source) reuse its chunk list
+    let previous: std::collections::HashMap<String, SnapshotFile> = load_snapshots(repo)
+        .into_iter()
+        .rfind(|s| s.source == source)
+        .map(|s| s.files.into_iter().map(|f| (f.path.clone(), f)).collect())
+        .unwrap_or_default();
+
+    let base = if source.is_file() { source.parent().unwrap_or(Path::new("/")) } else { source.as_path() };
+    let inputs: Vec<PathBuf> = zip_inputs(&source, opts)
+        .into_iter()
+        .filter(|p| !p.starts_with(&repo_abs) && p.as_path() != base)
+        .collect();
+
+    let bar = indicatif::ProgressBar::new(inputs.len() as u64);
+    bar.set_style(indicatif::ProgressStyle::with_template("  {bar:30.cyan/blue} {pos}/{len} {wide_msg}")
+        .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar()));
+
+    let (mut files, mut reused, mut written) = (Vec::new(), 0usize, 0u64);
+    for path in &inputs {
+        bar.inc(1);
+        let rel = path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        let Ok(meta) = path.metadata() else { continue };
+        let (mode, mtime) = file_mode_mtime(&meta);
+        if meta.is_dir() {
+            files.push(SnapshotFile { path: rel, dir: true, size: 0, mode, mtime, chunks: Vec::new(), sha256: None });
+            continue;
+        }
+        if let Some(prev) = previous.get(&rel).filter(|p| !p.dir && p.size == meta.len() && p.mtime == mtime) {
+            files.push(prev.clone());
+            reused += 1;
+            continue;
+        }
+        bar.set_message(rel.clone());
+        match store_file(repo, path, level) {
+            Ok((chunks, sha256, w)) => {
+                written += w;
+                let sha256 = Some(sha256);
+                files.push(SnapshotFile { path: rel, dir: false, size: meta.len(), mode, mtime, chunks, sha256 });
+            }
+            Err(e) => bar.suspend(|| eprintln!("  {} {}: {}", "✗".red(), path.display(), e)),
+        }
+    }
+    bar.finish_and_clear();
+
+    let now = chrono::Local::now();
+    let mut id = now.format("%Y%m%d_%H%M%S").to_string();
+    let mut n = 2;
+    while repo.join("snapshots").join(format!("{id}.json")).exists() {
+        id = format!("{}_{n}", now.format("%Y%m%d_%H%M%S"));
+        n += 1;
+    }
+    let snap = Snapshot { id, source, created: now.to_rfc3339(), files, filter: BackupFilter::from_opts(opts) };
+    let manifest = repo.join("snapshots").join(format!("{}.json", snap.id));
+    if let Err(e) = std::fs::write(&manifest, serde_json::to_vec_pretty(&snap).unwrap()) {
+        drop(lock);
+        eprintln!("{} Cannot write manifest {}: {}", "✗".red(), manifest.display(), e);
+        std::process::exit(1);
+    }
+    drop(lock);
+
+    let count = snap.files.iter().filter(|f| !f.dir).count();
+    println!("{} Snapshot {} → {}",
+        "✓".green().bold(), snap.id.yellow().bold(), repo.display().to_string().cyan());
+    println!("  {} file(s), {}  ·  {} unchanged  ·  {} new data stored",
+        count, human_size(snap.total_size()).yellow(), reused, human_size(written).green());
+}
+
+fn open_repo(repo: &Path) -> Vec<Snapshot> {
+    if !is_backup_repo(repo) {
+        eprintln!("{} Not a backup repository: {}", "✗".red(), repo.display());
+        std::process::exit(1);
+    }
+    load_snapshots(repo)
+}
+
+fn cmd_backup_list(repo: &Path) {
+    let snaps = open_repo(repo);
+    if snaps.is_empty() {
+        println!("{}", "No snapshots yet.".yellow());
+        return;
+    }
+
+    println!("\n  {:<20} {:<8} {:<12} {}",
+        "ID".bold().underline(), "FILES".bold().underline(),
+        "SIZE".bold().underline(), "SOURCE".bold().underline());
+    for s in &snaps {
+        let files = s.files.iter().filter(|f| !f.dir).count();
+        println!("  {:<20} {:<8} {:<12} {}",
+            s.id.yellow(), files, human_size(s.total_size()), s.source.display().to_string().cyan());
+    }
+    let logical: u64 = snaps.iter().map(|s| s.total_size()).sum();
+    println!("\n  {} snapshot(s), {} logical, {} on disk\n",
+        snaps.len(), human_size(logical).yellow(), human_size(dir_size(&repo.join("chunks"))).green());
+}
+
+fn find_snapshot(snaps: Vec<Snapshot>, id: &str) -> Snapshot {
+    let mut matches: Vec<Snapshot> = if id == "latest" {
+        snaps.into_iter().next_back().into_iter().collect()
+    } else if let Some(exact) = snaps.iter().position(|s| s.id == id) {
+        vec![snaps.into_iter().nth(exact).unwrap()]
+    } else {
+        snaps.into_iter().filter(|s| s.id.starts_with(id)).collect()
+    };
+    match matches.len() {
+        1 => matches.remove(0),
+        0 => {
+            eprintln!("{} No snapshot matches '{}'", "✗".red(), id);
+            std::process::exit(1);
+        }
+        n => {
+            eprintln!("{} '{}' is ambiguous ({} snapshots match)", "✗".red(), id, n);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cmd_backup_restore(repo: &Path, id: &str, only: Option<&str>, to: Option<PathBuf>, overwrite: bool) {
+    use std::io::Write;
+
+    let snap = find_snapshot(open_repo(repo), id);
+    let to = to.unwrap_or_else(|| {
+        PathBuf::from(snap.source.file_name().unwrap_or(std::ffi::OsStr::new("restore")))
+    });
+    let only = only.map(|p| p.trim_start_matches("./").trim_end_matches('/'));
+    let selected: Vec<&SnapshotFile> = snap.files.iter()
+        .filter(|f| only.is_none_or(|p| f.path == p || f.path.starts_with(&format!("{p}/"))))
+        .collect();
+    if selected.is_empty() {
+        eprintln!("{} Nothing in snapshot {} matches '{}'", "✗".red(), snap.id, only.unwrap_or(""));
+        std::process::exit(1);
+    }
+
+    let (mut restored, mut skipped, mut failed) = (0usize, 0usize, 0usize);
+    for f in selected {
+        // Like enclosed_name() in unzip: a snapshot path may only hold plain names, so a
+        // tampered manifest can't reach outside `to` through `..`, a root or a drive prefix.
+        let rel = Path::new(&f.path);
+        if rel.as_os_str().is_empty() || !rel.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+            eprintln!("  {} {}: unsafe path in snapshot, skipped", "✗".red(), f.path);
+            failed += 1;
+            continue;
+        }
+        let out = to.join(rel);
+        if f.dir {
+            std::fs::create_dir_all(&out).ok();
+            continue;
+        }
+        if out.exists() && !overwrite {
+            skipped += 1;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let result = std::fs::File::create(&out).and_then(|mut w| {
+            for hash in &f.chunks {
+                w.write_all(&get_chunk(repo, hash)?)?;
+            }
+            let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(f.mtime.max(0) as u64);
+            w.set_modified(mtime)
+        });
+        match result {
+            Ok(()) => restored += 1,
+            Err(e) => {
+                eprintln!("  {} {}: {}", "✗".red(), f.path, e);
+                failed += 1;
+            }
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&out, std::fs::Permissions::from_mode(f.mode)).ok();
+        }
+    }
+
+    println!("{} Restored {} file(s) from {} → {}",
+        "✓".green().bold(), restored, snap.id.yellow(), to.display().to_string().cyan());
+    if skipped > 0 {
+        println!("  {} {} existing file(s) left untouched (use --overwrite)", "!".yellow(), skipped);
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  BACKUP PRUNE  — retention policies
+// ─────────────────────────────────────────────────────────────
+struct PruneItem {
+    /// Backups only compete with others of the same series (zip name / repo source)
+    series: String,
+    when: chrono::NaiveDateTime,
+    label: String,
+    size: u64,
+    chunks: Vec<String>,
+}
+
+/// Split `name_YYYYMMDD_HHMMSS.zip` into ("name", timestamp)
+fn parse_backup_name(file_name: &str) -> Option<(String, chrono::NaiveDateTime)> {
+    let stem = file_name.strip_suffix(".zip")?;
+    if stem.len() < 17 || !stem.is_char_boundary(stem.len() - 16) { return None; }
+    let (series, stamp) = stem.split_at(stem.len() - 16);
+    let stamp = stamp.strip_prefix('_')?;
+    let when = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok()?;
+    Some((series.to_string(), when))
+}
+
+fn prune_items(dir: &Path) -> Vec<PruneItem> {
+    if is_backup_repo(dir) {
+        return load_snapshots(dir)
+            .into_iter()
+            .filter_map(|s| {
+                let when = chrono::NaiveDateTime::parse_from_str(s.id.get(..15)?, "%Y%m%d_%H%M%S").ok()?;
+                Some(PruneItem {
+                    series: s.source.display().to_string(),
+                    when,
+                    size: s.total_size(),
+                    chunks: s.files.into_iter().flat_map(|f| f.chunks).collect(),
+                    label: s.id,
+                })
+            })
+            .collect();
+    }
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let label = e.file_name().to_string_lossy().to_string();
+            let (series, when) = parse_backup_name(&label)?;
+            let size = e.metadata().ok()?.len();
+            Some(PruneItem { series, when, label, size, chunks: Vec::new() })
+        })
+        .collect()
+}
+
+/// For each item, the retention rules that keep it (empty = remove)
+fn retention_reasons(items: &[PruneItem], policy: &RetentionArgs) -> Vec<Vec<&'static str>> {
+    use chrono::Datelike;
+    type Key = fn(&PruneItem) -> String;
+    let rules: [(&str, Option<usize>, Key); 4] = [
+        ("last",    policy.keep_last,    |i| i.label.clone()),
+        ("daily",   policy.keep_daily,   |i| i.when.format("%Y-%m-%d").to_string()),
+        ("weekly",  policy.keep_weekly,  |i| format!("{}-W{:02}", i.when.iso_week().year(), i.when.iso_week().week())),
+        ("monthly", policy.keep_monthly, |i| i.when.format("%Y-%m").to_string()),
+    ];
+    let no_rules = rules.iter().all(|r| r.1.is_none());
+
+    let mut series: std::collections::BTreeMap<&str, Vec<usize>> = std::collections::BTreeMap::new();
+    for (i, item) in items.iter().enumerate() {
+        series.entry(&item.series).or_default().push(i);
+    }
+
+    let mut reasons = vec![Vec::new(); items.len()];
+    for idxs in series.values_mut() {
+        // same-second backups get "_2", "_3" … ids, so the longer/greater id is the newer one
+        idxs.sort_by_key(|&i| std::cmp::Reverse((items[i].when, items[i].label.len(), &items[i].label)));
+        if no_rules {
+            idxs.iter().for_each(|&i| reasons[i].push("no policy"));
+            continue;
+        }
+        for (name, n, key) in &rules {
+            let Some(n) = *n else { continue };
+            let mut last: Option<String> = None;
+            let mut kept = 0;
+            for &i in idxs.iter() {
+                if kept >= n { break; }
+                let k = key(&items[i]);
+                if last.as_ref() != Some(&k) {
+                    reasons[i].push(*name);
+                    kept += 1;
+                    last = Some(k);
+                }
+            }
+        }
+    }
+    reasons
+}
+
+fn cmd_backup_prune(dir: &Path, policy: &RetentionArgs, dry_run: bool, yes: bool) {
+    let repo = is_backup_repo(dir);
+    // a snapshot we can't read still references chunks; collecting garbage without it would eat them
+    if repo {
+        let (_, broken) = read_snapshots(dir);
+        if !broken.is_empty() {
+            eprintln!("{} Cannot prune, {} snapshot(s) are unreadable:", "✗".red(), broken.len());
+            for b in &broken { eprintln!("    {}", b); }
+            std::process::exit(1);
+        }
+    }
+    let mut items = prune_items(dir);
+    if items.is_empty() {
+        println!("{}", format!("No timestamped backups found in {}", dir.display()).yellow());
+        return;
+    }
+    items.sort_by(|a, b| (&a.series, a.when, a.label.len(), &a.label).cmp(&(&b.series, b.when, b.label.len(), &b.label)));
+
+    // repo snapshots share chunks, so their real footprint is the set of chunks still referenced
+    let chunk_sizes: std::collections::HashMap<String, u64> = if repo {
+        walkdir::WalkDir::new(dir.join("chunks"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| (e.file_name().to_string_lossy().to_string(), e.metadata().map(|m| m.len()).unwrap_or(0)))
+            .collect()
+    } else {
+        Default::default()
+    };
+    let footprint = |keep: &[bool]| -> u64 {
+        let kept = items.iter().zip(keep).filter(|(_, k)| **k).map(|(i, _)| i);
+        if repo {
+            let live: std::collections::HashSet<&String> = kept.flat_map(|i| &i.chunks).collect();
+            live.iter().map(|h| chunk_sizes.get(*h).copied().unwrap_or(0)).sum()
+        } else {
+            kept.map(|i| i.size).sum()
+        }
+    };
+
+    let mut reasons = retention_reasons(&items, policy);
+    let mut keep: Vec<bool> = reasons.iter().map(|r| !r.is_empty()).collect();
+
+    if let Some(cap) = policy.max_total_size {
+        let mut by_age: Vec<usize> = (0..items.len()).collect();
+        by_age.sort_by_key(|&i| items[i].when);
+        for i in by_age {
+            if footprint(&keep) <= cap { break; }
+            let siblings = (0..items.len()).filter(|&j| keep[j] && items[j].series == items[i].series).count();
+            // never drop the last backup of a series just to meet the cap
+            if keep[i] && siblings > 1 {
+                keep[i] = false;
+                reasons[i] = vec!["over size cap"];
+            }
+        }
+    }
+
+    // whatever the policy says, the newest backup of every series stays
+    let mut newest: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        newest.insert(&item.series, i); // items are sorted oldest → newest
+    }
+    let mut newest: Vec<usize> = newest.into_values().filter(|&i| !keep[i]).collect();
+    newest.sort();
+    if !newest.is_empty() {
+        for i in newest {
+            eprintln!("{} This policy would remove {}, the newest backup of {}",
+                "✗".red(), items[i].label, items[i].series);
+        }
+        eprintln!("  {}", "Refusing to prune; keep at least the latest backup (e.g. --keep-last 1).".dimmed());
+        std::process::exit(1);
+    }
+
+    println!("\n  {:<8} {:<36} {:<12} {}",
+        "ACTION".bold().underline(), "BACKUP".bold().underline(),
+        "SIZE".bold().underline(), "REASON".bold().underline());
+    for (i, item) in items.iter().enumerate() {
+        let action = if keep[i] { "keep".green() } else { "remove".red().bold() };
+        let why = if keep[i] || reasons[i].is_empty() { reasons[i].join(", ") } else { reasons[i][0].to_string() };
+        println!("  {:<8} {:<36} {:<12} {}", action, item.label, human_size(item.size), why.dimmed());
+    }
+
+    let doomed: Vec<usize> = (0..items.len()).filter(|&i| !keep[i]).collect();
+    let before = footprint(&vec![true; items.len()]);
+    let after = footprint(&keep);
+    if doomed.is_empty() {
+        println!("\n{} Nothing to prune ({} kept).", "✓".green().bold(), human_size(before));
+        return;
+    }
+    println!("\n{} {} backup(s), freeing {}  ({} → {})",
+        "Will remove".yellow().bold(), doomed.len(),
+        human_size(before - after).green(), human_size(before), human_size(after));
+
+    if dry_run {
+        println!("{}", "(dry-run) nothing deleted.".dimmed());
+        return;
+    }
+    if !yes && !confirm("\nProceed?") { return; }
+
+    // backups finished by now are in the fresh snapshot list below; new ones wait for us
+    let lock = repo.then(|| lock_repo(dir));
+    let mut removed = 0usize;
+    for &i in &doomed {
+        let target = if repo {
+            dir.join("snapshots").join(format!("{}.json", items[i].label))
+        } else {
+            dir.join(&items[i].label)
+        };
+        match std::fs::remove_file(&target) {
+            Ok(_) => removed += 1,
+            Err(e) => eprintln!("  {} {}: {}", "✗".red(), target.display(), e),
+        }
+        if !repo {
+            std::fs::remove_file(manifest_path(&target)).ok();
+        }
+    }
+    if repo {
+        let (snaps, broken) = read_snapshots(dir);
+        if !broken.is_empty() {
+            drop(lock);
+            eprintln!("{} Skipping chunk collection, unreadable snapshot(s): {}", "✗".red(), broken.join(", "));
+            std::process::exit(1);
+        }
+        let live: std::collections::HashSet<String> = snaps
+            .into_iter()
+            .flat_map(|s| s.files.into_iter().flat_map(|f| f.chunks))
+            .collect();
+        let orphans = chunk_sizes.keys().filter(|h| !live.contains(*h));
+        let gc = orphans.filter(|h| std::fs::remove_file(chunk_path(dir, h)).is_ok()).count();
+        println!("  {} unreferenced chunk(s) collected", gc);
+    }
+    drop(lock);
+    println!("{} Pruned {} backup(s).", "✓".green().bold(), removed);
+}
+
+// ─────────────────────────────────────────────────────────────
+//  BACKUP VERIFY  — CRCs, SHA-256 manifests, drift
+// ─────────────────────────────────────────────────────────────
+/// `name_YYYYMMDD_HHMMSS.zip` → `name_YYYYMMDD_HHMMSS.zip.sha256` (sha256sum format)
+fn manifest_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+const MANIFEST_FILTER: &str = "# vasu-filter: ";
+
+fn read_manifest(path: &Path) -> Option<std::collections::BTreeMap<String, String>> {
+    let text = std::fs::read_to_string(path).ok()?;
+    Some(text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once("  "))
+        .map(|(hash, name)| (name.to_string(), hash.to_string()))
+        .collect())
+}
+
+/// The filter line written by newer backups; older manifests have none
+fn read_manifest_filter(path: &Path) -> BackupFilter {
+    std::fs::read_to_string(path).ok()
+        .and_then(|text| text.lines()
+            .find_map(|l| l.strip_prefix(MANIFEST_FILTER))
+            .and_then(|json| serde_json::from_str(json).ok()))
+        .unwrap_or_default()
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn verify_bar(len: usize) -> indicatif::ProgressBar {
+    let bar = indicatif::ProgressBar::new(len as u64);
+    bar.set_style(indicatif::ProgressStyle::with_template("  {bar:30.cyan/blue} {pos}/{len} {wide_msg}")
+        .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar()));
+    bar
+}
+
+fn cmd_backup_verify(target: &Path, source: Option<&Path>, id: Option<&str>, password_file: Option<&Path>) {
+    let (problems, expected, base, filter) = if is_backup_repo(target) {
+        verify_repo(target, id, source)
+    } else {
+        verify_zip(target, password_file, source)
+    };
+
+    let drift = match (source, &base) {
+        (Some(src), Some(base)) => report_drift(&expected, src, base, &filter),
+        _ => 0,
+    };
+
+    if problems > 0 {
+        eprintln!("\n{} {} problem(s) found — this backup is NOT fully restorable.", "✗".red().bold(), problems);
+        std::process::exit(1);
+    }
+    if source.is_some() && base.is_some() && drift == 0 {
+        println!("{} Source matches the backup.", "✓".green().bold());
+    }
+}
+
+/// What a verify pass hands to `report_drift`: problem count, entry → SHA-256,
+/// the base dir live paths are relative to, and the filter the backup used
+type VerifyResult = (usize, std::collections::BTreeMap<String, String>, Option<PathBuf>, BackupFilter);
+
+fn verify_zip(archive: &Path, password_file: Option<&Path>, source: Option<&Path>) -> VerifyResult {
+    use sha2::{Digest, Sha256};
+
+    let mut zip = open_zip(archive);
+    let stored = read_manifest(&manifest_path(archive));
+    let encrypted = (0..zip.len()).any(|i| zip.by_index_raw(i).is_ok_and(|f| f.encrypted()));
+    let password = encrypted.then(|| zip_password(password_file, false));
+
+    let bar = verify_bar(zip.len());
+    let mut hashes = std::collections::BTreeMap::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut problems = 0usize;
+    for i in 0..zip.len() {
+        bar.inc(1);
+        let file = match &password {
+            Some(pw) => zip.by_index_decrypt(i, pw.as_bytes()),
+            None => zip.by_index(i),
+        };
+        let mut f = match file {
+            Ok(f) => f,
+            Err(e) => {
+                bar.suspend(|| eprintln!("  {} entry #{}: {}", "✗".red(), i, e));
+                problems += 1;
+                continue;
+            }
+        };
+        if f.is_dir() { continue; }
+        let name = f.name().to_string();
+        seen.insert(name.clone());
+        let mut hasher = Sha256::new();
+        // the zip reader checks the CRC-32 (and AES HMAC) once the entry is fully read
+        if let Err(e) = std::io::copy(&mut f, &mut hasher) {
+            bar.suspend(|| eprintln!("  {} {}: {}", "✗".red(), name, e));
+            problems += 1;
+            continue;
+        }
+        let hash = format!("{:x}", hasher.finalize());
+        if let Some(want) = stored.as_ref().and_then(|m| m.get(&name)) {
+            if *want != hash {
+                bar.suspend(|| eprintln!("  {} {}: SHA-256 differs from manifest", "✗".red(), name));
+                problems += 1;
+            }
+        }
+        hashes.insert(name, hash);
+    }
+    bar.finish_and_clear();
+
+    let mode = if let Some(m) = &stored {
+        for name in m.keys().filter(|n| !seen.contains(*n)) {
+            eprintln!("  {} {}: listed in manifest but missing from archive", "✗".red(), name);
+            problems += 1;
+        }
+        "CRC + SHA-256 manifest"
+    } else {
+        "CRC only, no .sha256 manifest"
+    };
+    if problems == 0 {
+        println!("{} {} entries OK  ({})", "✓".green().bold(), hashes.len(), mode.dimmed());
+    }
+
+    // zip entries are named relative to the source's parent (they start with its folder name)
+    let base = source
+        .and_then(|s| s.canonicalize().ok())
+        .map(|s| s.parent().map(Path::to_path_buf).unwrap_or(s));
+    (problems, hashes, base, read_manifest_filter(&manifest_path(archive)))
+}
+
+fn verify_repo(repo: &Path, id: Option<&str>, source: Option<&Path>) -> VerifyResult {
+    use sha2::{Digest, Sha256};
+    use std::collections::HashSet;
+
+    let all = open_repo(repo);
+    let snaps = match id {
+        Some(id) => vec![find_snapshot(all, id)],
+        None => all,
+    };
+
+    let total: usize = snaps.iter().map(|s| s.files.len()).sum();
+    let bar = verify_bar(total);
+    let mut good_chunks: HashSet<String> = HashSet::new();
+    let mut bad_chunks: HashSet<String> = HashSet::new();
+    let mut checked_files: HashSet<(String, Vec<String>)> = HashSet::new();
+    let mut problems = 0usize;
+
+    for snap in &snaps {
+        for f in snap.files.iter().filter(|f| !f.dir) {
+            bar.inc(1);
+            let key = (f.sha256.clone().unwrap_or_default(), f.chunks.clone());
+            if checked_files.contains(&key) { continue; }
+
+            let mut hasher = Sha256::new();
+            let mut size = 0u64;
+            let mut broken = false;
+            for hash in &f.chunks {
+                if bad_chunks.contains(hash) { broken = true; break; }
+                match get_chunk(repo, hash) {
+                    Ok(data) => {
+                        size += data.len() as u64;
+                        hasher.update(&data);
+                        good_chunks.insert(hash.clone());
+                    }
+                    Err(e) => {
+                        bar.suspend(|| eprintln!("  {} chunk {}: {}", "✗".red(), &hash[..12.min(hash.len())], e));
+                        bad_chunks.insert(hash.clone());
+                        broken = true;
+                        break;
+                    }
+                }
+            }
+            let digest = format!("{:x}", hasher.finalize());
+            if broken {
+                bar.suspend(|| eprintln!("  {} {} [{}]: unreadable", "✗".red(), f.path, snap.id));
+                problems += 1;
+            } else if size != f.size || f.sha256.as_ref().is_some_and(|want| *want != digest) {
+                bar.suspend(|| eprintln!("  {} {} [{}]: content differs from manifest", "✗".red(), f.path, snap.id));
+                problems += 1;
+            } else {
+                checked_files.insert(key);
+            }
+        }
+    }
+    bar.finish_and_clear();
+
+    if problems == 0 {
+        println!("{} {} snapshot(s), {} chunk(s) OK  ({})",
+            "✓".green().bold(), snaps.len(), good_chunks.len(), "SHA-256 per chunk and file".dimmed());
+    }
+
+    // drift is measured against the chosen snapshot, or the newest one of that source;
+    // its paths are relative to the source
+    let base = source.and_then(|s| s.canonicalize().ok());
+    let against = match (id, &base) {
+        (Some(_), _) => snaps.last(),
+        (None, Some(src)) => snaps.iter().rfind(|s| s.source == *src),
+        (None, None) => None,
+    };
+    if let (None, Some(src)) = (against, &base) {
+        println!("{} No snapshot of {} in this repository, skipping the drift check.",
+            "!".yellow(), src.display());
+    }
+    let expected = against
+        .map(|s| s.files.iter()
+            .filter(|f| !f.dir)
+            .map(|f| (f.path.clone(), f.sha256.clone().unwrap_or_default()))
+            .collect())
+        .unwrap_or_default();
+    let filter = against.map(|s| s.filter.clone()).unwrap_or_default();
+    (problems, expected, against.and(base), filter)
+}
+
+/// Hash the live source and compare it with what the backup holds.
+fn report_drift(
+    expected: &std::collections::BTreeMap<String, String>,
+    source: &Path,
+    base: &Path,
+    filter: &BackupFilter,
+) -> usize {
+    let source = source.canonicalize().unwrap_or_else(|_| {
+        eprintln!("{} Source not found: {}", "✗".red(), source.display());
+        std::process::exit(1);
+    });
+    let live: std::collections::BTreeMap<String, PathBuf> = zip_inputs(&source, &filter.zip_args())
+        .into_iter()
+        .filter(|p| p.is_file())
+        .map(|p| (p.strip_prefix(base).unwrap_or(&p).to_string_lossy().replace('\\', "/"), p))
+        .collect();
+
+    let mut rows: Vec<(ColoredString, &str)> = Vec::new();
+    for (name, hash) in expected {
+        match live.get(name) {
+            None => rows.push(("deleted ".red(), name)),
+            // older repo manifests have no file hash to compare against
+            Some(p) if !hash.is_empty() && sha256_file(p).ok().as_ref() != Some(hash) =>
+                rows.push(("modified".yellow(), name)),
+            _ => {}
+        }
+    }
+    for name in live.keys().filter(|n| !expected.contains_key(*n)) {
+        rows.push(("new     ".green(), name));
+    }
+
+    if !rows.is_empty() {
+        println!("\n  Drift since backup ({} vs {}):\n", "backup".cyan(), source.display().to_string().cyan());
+        for (tag, name) in &rows {
+            println!("  {}  {}", tag, name.dimmed());
+        }
+        println!();
+    }
+    rows.len()
+}
+
+// ─────────────────────────────────────────────────────────────
+//  BACKUP PROFILES  — [profiles.<name>] in vasu.toml
+// ─────────────────────────────────────────────────────────────
+#[derive(serde::Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum BackupFormat {
+    #[default]
+    Zip,
+    Incremental,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackupProfile {
+    source: PathBuf,
+    dest: PathBuf,
+    #[serde(default)]
+    format: BackupFormat,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    gitignore: bool,
+    level: Option<i32>,
+    #[serde(default)]
+    store: bool,
+    #[serde(default)]
+    encrypt: bool,
+    password_file: Option<PathBuf>,
+    /// Applied with `backup prune` right after each run
+    retention: Option<RetentionArgs>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: std::collections::BTreeMap<String, BackupProfile>,
+}
+
+fn load_profiles() -> (PathBuf, ProfilesFile) {
+    let Some(path) = find_upward("vasu.toml") else {
+        eprintln!("{} No vasu.toml found in this directory or any parent.", "✗".red());
+        std::process::exit(1);
+    };
+    let parsed = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str::<ProfilesFile>(&text).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(file) => (path, file),
+        Err(e) => {
+            eprintln!("{} {}: {}", "✗".red(), path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cmd_backup_profiles(only: Option<&str>, cli: &ZipArgs) {
+    let (path, file) = load_profiles();
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let selected: Vec<(&String, &BackupProfile)> = match only {
+        Some(name) => match file.profiles.get_key_value(name) {
+            Some(p) => vec![p],
+            None => {
+                let known: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+                eprintln!("{} No profile '{}' in {}  (available: {})",
+                    "✗".red(), name, path.display(), if known.is_empty() { "none".into() } else { known.join(", ") });
+                std::process::exit(1);
+            }
+        },
+        None => file.profiles.iter().collect(),
+    };
+    if selected.is_empty() {
+        println!("{}", format!("No [profiles.*] defined in {}", path.display()).yellow());
+        return;
+    }
+
+    for (name, profile) in selected {
+        if profile.level.is_some_and(|l| !(0..=9).contains(&l)) {
+            eprintln!("{} Profile '{}': level must be 0-9", "✗".red(), name);
+            std::process::exit(1);
+        }
+        let incremental = profile.format == BackupFormat::Incremental;
+
+        // command-line flags add to whatever the profile sets; --no-store / --no-gitignore switch it off
+        let opts = ZipArgs {
+            level: cli.level.or(profile.level),
+            store: !cli.no_store && (cli.store || profile.store),
+            no_store: cli.no_store,
+            exclude: profile.exclude.iter().chain(&cli.exclude).cloned().collect(),
+            gitignore: !cli.no_gitignore && (cli.gitignore || profile.gitignore),
+            no_gitignore: cli.no_gitignore,
+            reproducible: cli.reproducible,
+            encrypt: cli.encrypt || profile.encrypt,
+            password_file: cli.password_file.clone()
+                .or_else(|| profile.password_file.as_ref().map(|p| expand_path(p, base))),
+        };
+        // checked after merging, so `--profile notes -e` can't silently write plaintext chunks;
+        // the config file comes in only afterwards, and its `store` means nothing to a repository
+        if incremental && (opts.encrypt || opts.store) {
+            eprintln!("{} Profile '{}': encrypt/store only apply to zip backups", "✗".red(), name);
+            std::process::exit(1);
+        }
+        let mut opts = opts.with_config();
+        if incremental { opts.store = false; }
+        let source = expand_path(&profile.source, base);
+        let dest = expand_path(&profile.dest, base);
+
+        println!("\n{} {}  {} → {}", "▶ profile".cyan().bold(), name.yellow().bold(),
+            source.display(), dest.display());
+        cmd_backup(&source, &dest, &opts, incremental);
+        if let Some(policy) = &profile.retention {
+            cmd_backup_prune(&dest, policy, false, true);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  ENV
+// ─────────────────────────────────────────────────────────────
+fn cmd_env(filter: &str) {
+    let lower = filter.to_lowercase();
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(k, v)| {
+            filter.is_empty()
+                || k.to_lowercase().contains(&lower)
+                || v.to_lowercase().contains(&lower)
+        })
+        .collect();
+    vars.sort_by(|a, b| a.0.cmp(&b.0));
+
+    println!();
+    for (k, v) in &vars {
+        println!("  {}={}", k.cyan().bold(), v.yellow());
+    }
+    println!();
+}
+
+// ─────────────────────────────────────────────────────────────
+//  HTTP
+// ─────────────────────────────────────────────────────────────
+fn cmd_http(port: u16, directory: &Path) {
+    use std::net::TcpListener;
+    use std::io::{Write, BufRead, BufReader};
+
+    let dir = directory.canonicalize().unwrap_or(directory.to_path_buf());
+    std::env::set_current_dir(&dir).unwrap();
+
+    println!("{} Serving {} at {}",
+        "⚡".yellow(),
+        dir.display().to_string().yellow(),
+        format!("http://localhost:{port}").cyan().bold(),
+    );
+    println!("{}", "Press Ctrl-C to stop\n".dimmed());
+
+    let listener = TcpListener::bind(format!("0.0.0.0:{port}")).expect("Failed to bind port");
+
+    for mut stream in listener.incoming().flatten() {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut first_line = String::new();
+            reader.read_line(&mut first_line).ok();
+
+            let path_str = first_line
+                .split_whitespace().nth(1).unwrap_or("/")
+                .to_string();
+            let decoded = percent_decode(&path_str);
+            let rel_path = decoded.trim_start_matches('/');
+            let full_path = if rel_path.is_empty() {
+                dir.clone()
+            } else {
+                dir.join(rel_path)
+            };
+
+            let response = if full_path.is_dir() {
+                let mut body = format!(
+                    "<html><head><meta charset='utf-8'></head><body><h2>📁 {}</h2><ul>",
+                    full_path.display()
+                );
+                if let Ok(entries) = std::fs::read_dir(&full_path) {
+                    let mut items: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+                    items.sort_by_key(|e| e.file_name());
+                    for item in items {
+                        let n = item.file_name().to_string_lossy().to_string();
+                        let href = format!("{}/{}", path_str.trim_end_matches('/'), n);
+                        let icon = if item.path().is_dir() { "📁" } else { "📄" };
+                        body.push_str(&format!("<li>{icon} <a href='{href}'>{n}</a></li>"));
+                    }
+                }
+                body.push_str("</ul></body></html>");
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(), body)
+            } else if full_path.is_file() {
+                match std::fs::read(&full_path) {
+                    Ok(data) => {
+                        println!("  {} {}", "GET".green(), path_str);
+                        let mime = guess_mime(full_path.extension()
+                            .unwrap_or_default().to_str().unwrap_or(""));
+                        let header = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+                            mime, data.len()
+                        );
+                        stream.write_all(header.as_bytes()).ok();
+                        stream.write_all(&data).ok();
+                        continue;
+                    }
+                    Err(_) => "HTTP/1.1 500 Internal Server Error\r\n\r\n".to_string(),
+                }
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\n404 oops".to_string()
+            };
+
+            stream.write_all(response.as_bytes()).ok();
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let h: String = chars.by_ref().take(2).collect();
+            if let Ok(byte) = u8::from_str_radix(&h, 16) {
+                out.push(byte as char);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn guess_mime(ext: &str) -> &'static str {
+    match ext {
+        "html" | "htm" => "text/html",
+        "css"          => "text/css",
+        "js"           => "application/javascript",
+        "json"         => "application/json",
+        "png"          => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif"          => "image/gif",
+        "svg"          => "image/svg+xml",
+        "pdf"          => "application/pdf",
+        "txt" | "md"   => "text/plain",
+        _              => "application/octet-stream",
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  DIFF
+// ─────────────────────────────────────────────────────────────
+fn cmd_diff(dir_a: &Path, dir_b: &Path) {
+    use std::collections::HashSet;
+
+    fn all_files(root: &Path) -> HashSet<String> {
+        walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.path().strip_prefix(root).unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    let fa = all_files(dir_a);
+    let fb = all_files(dir_b);
+
+    let only_a: Vec<_> = fa.difference(&fb).collect();
+    let only_b: Vec<_> = fb.difference(&fa).collect();
+    let changed: Vec<_> = fa.intersection(&fb)
+        .filter(|f| {
+            let a = std::fs::read(dir_a.join(f)).unwrap_or_default();
+            let b = std::fs::read(dir_b.join(f)).unwrap_or_default();
+            a != b
+        })
+        .collect();
+
+    if only_a.is_empty() && only_b.is_empty() && changed.is_empty() {
+        println!("{}", "✓ Directories are identical.".green().bold());
+        return;
+    }
+
+    println!("\n  Diff: {}  vs  {}\n",
+        dir_a.display().to_string().cyan(),
+        dir_b.display().to_string().cyan());
+
+    for f in only_a  { println!("  {}  {}", "only in A".red(),     f.dimmed()); }
+    for f in only_b  { println!("  {}  {}", "only in B".green(),   f.dimmed()); }
+    for f in changed { println!("  {}   {}", "modified".yellow(), f.dimmed()); }
+    println!();
+}
+
+// ─────────────────────────────────────────────────────────────
+//  DUPE
+// ─────────────────────────────────────────────────────────────
+fn cmd_dupe(directory: &Path) {
+    use std::collections::HashMap;
+
+    let mut hashes: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    print!("Scanning… ");
+    for entry in walkdir::WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() { continue; }
+        let path = entry.into_path();
+        if let Ok(data) = std::fs::read(&path) {
+            let hash = format!("{:x}", md5::compute(&data));
+            hashes.entry(hash).or_default().push(path);
         }
     }
 
@@ -1025,21 +5053,348 @@ fn cmd_dupe(directory: &Path) {
         return;
     }
 
-    let total: usize = dupes.iter().map(|v| v.len() - 1).sum();
-    println!("\n{} group(s)  ({} extra copies):\n",
-        dupes.len().to_string().yellow().bold(),
-        total.to_string().red());
+    let total: usize = dupes.iter().map(|v| v.len() - 1).sum();
+    println!("\n{} group(s)  ({} extra copies):\n",
+        dupes.len().to_string().yellow().bold(),
+        total.to_string().red());
+
+    let base = directory.canonicalize().unwrap_or(directory.to_path_buf());
+    for group in &dupes {
+        let size = group[0].metadata().map(|m| m.len()).unwrap_or(0);
+        println!("  {} — {}", human_size(size).yellow(), format!("{} copies", group.len()).red());
+        for p in *group {
+            let rel = p.strip_prefix(&base).unwrap_or(p);
+            println!("    {}", rel.display().to_string().cyan());
+        }
+        println!();
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+//  CONFIG  — built-in defaults < ~/.config/vasu/config.toml < .vasu.toml < flags
+// ─────────────────────────────────────────────────────────────
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    cb: CbConfig,
+    tree: TreeConfig,
+    find: FindConfig,
+    size: SizeConfig,
+    clean: CleanConfig,
+    zip: ZipConfig,
+    backup: BackupConfig,
+    http: HttpConfig,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CbConfig {
+    no_header: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TreeConfig {
+    depth: usize,
+    all: bool,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self { Self { depth: 4, all: false } }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FindConfig {
+    r#type: String,
+}
+
+impl Default for FindConfig {
+    fn default() -> Self { Self { r#type: "all".into() } }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SizeConfig {
+    top: usize,
+}
+
+impl Default for SizeConfig {
+    fn default() -> Self { Self { top: 20 } }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CleanConfig {
+    /// Presets used when `--preset` isn't given
+    presets: Vec<String>,
+    /// Extra rules applied everywhere
+    rules: Vec<String>,
+    /// User presets: `[clean.preset.<name>]` with `markers` and `rules`
+    preset: std::collections::BTreeMap<String, CleanPresetConfig>,
+}
+
+impl Default for CleanConfig {
+    fn default() -> Self {
+        Self { presets: vec!["auto".into()], rules: Vec::new(), preset: Default::default() }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CleanPresetConfig {
+    markers: Vec<String>,
+    rules: Vec<String>,
+}
 
-    let base = directory.canonicalize().unwrap_or(directory.to_path_buf());
-    for group in &dupes {
-        let size = group[0].metadata().map(|m| m.len()).unwrap_or(0);
-        println!("  {} — {}", human_size(size).yellow(), format!("{} copies", group.len()).red());
-        for p in *group {
-            let rel = p.strip_prefix(&base).unwrap_or(p);
-            println!("    {}", rel.display().to_string().cyan());
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ZipConfig {
+    level: Option<i32>,
+    store: bool,
+    gitignore: bool,
+    exclude: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct BackupConfig {
+    dest: Option<PathBuf>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HttpConfig {
+    port: u16,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self { Self { port: 8080 } }
+}
+
+impl Config {
+    fn validate(&self) -> Result<(), String> {
+        if !["f", "d", "all"].contains(&self.find.r#type.as_str()) {
+            return Err(format!("find.type must be f, d or all (got '{}')", self.find.r#type));
         }
-        println!();
+        if self.zip.level.is_some_and(|l| !(0..=9).contains(&l)) {
+            return Err("zip.level must be 0-9".into());
+        }
+        if self.tree.depth == 0 {
+            return Err("tree.depth must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+/// The effective config, loaded once on first use
+fn config() -> &'static Config {
+    static CONFIG: std::sync::OnceLock<Config> = std::sync::OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let layers = config_layers();
+        let loaded = layers.iter()
+            .map(|(_, path)| read_config_table(path).map(|t| anchor_config_paths(t, path)))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|tables| build_config(tables.into_iter()).map_err(|e| {
+                let files: Vec<String> = layers.iter().map(|(_, p)| p.display().to_string()).collect();
+                format!("Invalid config ({}): {}", files.join(" + "), e)
+            }));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("{} {}", "✗".red(), e);
+            std::process::exit(1);
+        })
+    })
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+/// `$VASU_CONFIG`, else `$XDG_CONFIG_HOME/vasu/config.toml`, else `~/.config/vasu/config.toml`
+fn global_config_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("VASU_CONFIG").filter(|p| !p.is_empty()) {
+        return PathBuf::from(p);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".config")))
+        .unwrap_or_default()
+        .join("vasu")
+        .join("config.toml")
+}
+
+/// Existing config files, lowest precedence first
+fn config_layers() -> Vec<(&'static str, PathBuf)> {
+    let mut layers = Vec::new();
+    let global = global_config_path();
+    if global.is_file() {
+        layers.push(("global", global));
+    }
+    if let Some(project) = find_upward(".vasu.toml") {
+        layers.push(("project", project));
+    }
+    layers
+}
+
+fn read_config_table(path: &Path) -> Result<toml::Table, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Relative paths in a config file are relative to that file, not to wherever vasu runs
+fn anchor_config_paths(mut table: toml::Table, file: &Path) -> toml::Table {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    if let Some(toml::Value::String(dest)) = table.get_mut("backup").and_then(|b| b.get_mut("dest")) {
+        *dest = expand_path(Path::new(dest.as_str()), dir).to_string_lossy().into_owned();
+    }
+    table
+}
+
+fn merge_tables(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(b)), toml::Value::Table(o)) => merge_tables(b, o),
+            (_, v) => { base.insert(key, v); }
+        }
+    }
+}
+
+fn build_config(tables: impl Iterator<Item = toml::Table>) -> Result<Config, String> {
+    let mut merged = toml::Table::new();
+    for t in tables {
+        merge_tables(&mut merged, t);
+    }
+    let cfg: Config = merged.try_into().map_err(|e: toml::de::Error| e.message().to_string())?;
+    cfg.validate()?;
+    Ok(cfg)
+}
+
+/// `section.key` → value, for every leaf in the table
+fn flatten_table(table: &toml::Table) -> std::collections::BTreeMap<String, toml::Value> {
+    let mut out = std::collections::BTreeMap::new();
+    for (section, value) in table {
+        match value {
+            toml::Value::Table(t) => {
+                for (key, v) in t {
+                    out.insert(format!("{section}.{key}"), v.clone());
+                }
+            }
+            v => { out.insert(section.clone(), v.clone()); }
+        }
+    }
+    out
+}
+
+fn cmd_config(action: ConfigAction) {
+    match action {
+        ConfigAction::Path => {
+            let global = global_config_path();
+            let mark = |p: &Path| if p.is_file() { "✓".green() } else { "(not created)".dimmed() };
+            println!("  {} {}  {}", "global: ".bold(), global.display().to_string().cyan(), mark(&global));
+            match find_upward(".vasu.toml") {
+                Some(p) => println!("  {} {}  {}", "project:".bold(), p.display().to_string().cyan(), mark(&p)),
+                None => println!("  {} {}", "project:".bold(), "(no .vasu.toml in this directory or its parents)".dimmed()),
+            }
+        }
+        ConfigAction::List | ConfigAction::Get { .. } => {
+            let defaults = toml::Table::try_from(Config::default()).unwrap_or_default();
+            let mut effective: std::collections::BTreeMap<_, _> = flatten_table(&defaults)
+                .into_iter()
+                .map(|(k, v)| (k, (v, "default")))
+                .collect();
+            for (label, path) in config_layers() {
+                let table = read_config_table(&path).unwrap_or_else(|e| {
+                    eprintln!("{} {}", "✗".red(), e);
+                    std::process::exit(1);
+                });
+                for (k, v) in flatten_table(&table) {
+                    effective.insert(k, (v, label));
+                }
+            }
+
+            if let ConfigAction::Get { key } = action {
+                match effective.get(&key) {
+                    Some((toml::Value::String(s), _)) => println!("{s}"),
+                    Some((v, _)) => println!("{v}"),
+                    None => {
+                        eprintln!("{} '{}' is not set", "✗".red(), key);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+            println!();
+            for (key, (value, origin)) in &effective {
+                let origin = match *origin {
+                    "default" => origin.dimmed(),
+                    _ => origin.green(),
+                };
+                println!("  {:<20} = {}  {}", key.cyan(), value.to_string().yellow(), origin);
+            }
+            println!();
+        }
+        ConfigAction::Set { key, value, project } => config_set(&key, &value, project),
+    }
+}
+
+fn config_set(key: &str, value: &str, project: bool) {
+    let Some((section, field)) = key.split_once('.').filter(|(s, f)| !s.is_empty() && !f.is_empty()) else {
+        eprintln!("{} Keys look like section.name (e.g. tree.depth)", "✗".red());
+        std::process::exit(1);
+    };
+    let target = if project {
+        find_upward(".vasu.toml").unwrap_or_else(|| PathBuf::from(".vasu.toml"))
+    } else {
+        global_config_path()
+    };
+
+    let mut doc: toml_edit::DocumentMut = std::fs::read_to_string(&target)
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("{} {}: {}", "✗".red(), target.display(), e);
+            std::process::exit(1);
+        });
+    // anything that isn't valid TOML on its own is taken as a plain string
+    let parsed = format!("v = {value}").parse::<toml_edit::DocumentMut>().ok()
+        .and_then(|d| d.get("v").and_then(|v| v.as_value()).cloned())
+        .unwrap_or_else(|| toml_edit::Value::from(value));
+
+    let Some(table) = doc.entry(section).or_insert(toml_edit::table()).as_table_mut() else {
+        eprintln!("{} '{}' in {} is not a table", "✗".red(), section, target.display());
+        std::process::exit(1);
+    };
+    table[field] = toml_edit::value(parsed.clone());
+    let text = doc.to_string();
+
+    // validate the whole stack with the edited file in place before touching disk
+    let project_file = find_upward(".vasu.toml").or_else(|| project.then(|| target.clone()));
+    let tables = [Some(global_config_path()), project_file]
+        .into_iter()
+        .flatten()
+        .map(|p| if p == target {
+            toml::from_str(&text).map_err(|e: toml::de::Error| e.message().to_string())
+        } else if p.is_file() {
+            read_config_table(&p)
+        } else {
+            Ok(toml::Table::new())
+        })
+        .collect::<Result<Vec<_>, _>>();
+    if let Err(e) = tables.and_then(|t| build_config(t.into_iter())) {
+        eprintln!("{} Not saved: {}", "✗".red(), e);
+        std::process::exit(1);
+    }
+
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).ok();
+    }
+    if let Err(e) = std::fs::write(&target, text) {
+        eprintln!("{} {}: {}", "✗".red(), target.display(), e);
+        std::process::exit(1);
     }
+    println!("{} {} = {}  → {}",
+        "✓".green().bold(), key.cyan(), parsed.to_string().trim().yellow(), target.display().to_string().dimmed());
 }
 
 // ─────────────────────────────────────────────────────────────
@@ -1055,6 +5410,125 @@ fn human_size(size: u64) -> String {
     format!("{s:.1} PB")
 }
 
+/// Env var consulted for archive passwords before falling back to a prompt
+const PASSWORD_ENV: &str = "VASU_ZIP_PASSWORD";
+
+/// Resolve an archive password: `--password-file`, then `$VASU_ZIP_PASSWORD`,
+/// then an interactive prompt (asked twice when `confirm` is set).
+fn zip_password(file: Option<&Path>, confirm: bool) -> String {
+    if let Some(f) = file {
+        return match std::fs::read_to_string(f) {
+            Ok(s) if s.trim_end_matches(['\r', '\n']).is_empty() => {
+                eprintln!("{} Password file {} is empty", "✗".red(), f.display());
+                std::process::exit(1);
+            }
+            Ok(s) => s.trim_end_matches(['\r', '\n']).to_string(),
+            Err(e) => {
+                eprintln!("{} Cannot read password file {}: {}", "✗".red(), f.display(), e);
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Ok(pw) = std::env::var(PASSWORD_ENV) {
+        if !pw.is_empty() { return pw; }
+    }
+    let mut prompt = dialoguer::Password::new().with_prompt("Archive password");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat password", "Passwords don't match");
+    }
+    prompt.interact().unwrap_or_else(|e| {
+        eprintln!("{} No password given: {}", "✗".red(), e);
+        std::process::exit(1);
+    })
+}
+
+/// Parse "1536", "500K", "1.5G", "20GB" (binary units) into bytes
+fn parse_size(s: &str) -> Result<u64, String> {
+    let t = s.trim().to_uppercase();
+    let t = t.strip_suffix("IB").or_else(|| t.strip_suffix('B')).unwrap_or(&t);
+    let (num, mult) = match t.chars().last() {
+        Some('K') => (&t[..t.len() - 1], 1u64 << 10),
+        Some('M') => (&t[..t.len() - 1], 1 << 20),
+        Some('G') => (&t[..t.len() - 1], 1 << 30),
+        Some('T') => (&t[..t.len() - 1], 1 << 40),
+        _ => (t, 1),
+    };
+    num.trim().parse::<f64>()
+        .ok()
+        .filter(|n| *n >= 0.0)
+        .map(|n| (n * mult as f64) as u64)
+        .ok_or_else(|| format!("invalid size '{s}' (try 500K, 20M, 1.5G)"))
+}
+
+/// Parse "90s", "30m", "12h", "30d", "2w", "6mo", "1y" into a duration
+fn parse_age(s: &str) -> Result<std::time::Duration, String> {
+    let t = s.trim().to_lowercase();
+    let split = t.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let secs: f64 = match unit.trim() {
+        "s" | "sec" | "secs"              => 1.0,
+        "m" | "min" | "mins"              => 60.0,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+        "" | "d" | "day" | "days"         => 86400.0,
+        "w" | "wk" | "week" | "weeks"     => 7.0 * 86400.0,
+        "mo" | "month" | "months"         => 30.0 * 86400.0,
+        "y" | "yr" | "year" | "years"     => 365.0 * 86400.0,
+        _ => return Err(format!("invalid age '{s}' (try 30d, 12h, 2w, 1y)")),
+    };
+    num.parse::<f64>()
+        .ok()
+        .filter(|n| *n >= 0.0)
+        .map(|n| std::time::Duration::from_secs_f64(n * secs))
+        .ok_or_else(|| format!("invalid age '{s}' (try 30d, 12h, 2w, 1y)"))
+}
+
+/// Accept sizes in config files either as byte counts or as "20G"-style strings
+fn deserialize_size<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Size { Bytes(u64), Text(String) }
+    match <Option<Size> as serde::Deserialize>::deserialize(d)? {
+        None => Ok(None),
+        Some(Size::Bytes(n)) => Ok(Some(n)),
+        Some(Size::Text(t)) => parse_size(&t).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// `~/x` → `$HOME/x`; relative paths are taken relative to `base`
+fn expand_path(path: &Path, base: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = home_dir() {
+            return home.join(rest);
+        }
+    }
+    base.join(path)
+}
+
+/// Nearest `name` in the CWD or any of its parents
+fn find_upward(name: &str) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors().map(|d| d.join(name)).find(|p| p.is_file())
+}
+
+/// Replace a file's contents via a temp file + rename, keeping its permissions.
+/// A symlink is followed, so the file it points to is rewritten and the link stays a link.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let path = &std::fs::canonicalize(path)?;
+    let perms = std::fs::metadata(path)?.permissions();
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.vasu-tmp"));
+    let result = std::fs::File::create(&tmp)
+        .and_then(|mut f| f.write_all(data).and_then(|_| f.sync_all()))
+        .and_then(|_| std::fs::set_permissions(&tmp, perms))
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if result.is_err() {
+        std::fs::remove_file(&tmp).ok();
+    }
+    result
+}
+
 fn confirm(msg: &str) -> bool {
     use std::io::Write;
     print!("{} [y/N] ", msg.yellow());
@@ -1064,21 +5538,551 @@ fn confirm(msg: &str) -> bool {
     matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
-// ── Glob crate shim (we use the ignore crate for globs) ─────
+/// A file in the temp dir that is deleted again when dropped
+struct TempFile {
+    path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Create a new, randomly named file in the temp dir. `create_new` refuses to follow
+/// anything planted there beforehand, and on unix only we can read it.
+fn create_temp_file(prefix: &str) -> std::io::Result<TempFile> {
+    use std::hash::{BuildHasher, Hasher};
+    for _ in 0..16 {
+        let tag = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        let path = std::env::temp_dir().join(format!("{prefix}-{tag:016x}.txt"));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(_) => return Ok(TempFile { path }),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "no free temp file name"))
+}
+
+/// Write `text` to `path` (a file we created), open it in $VISUAL / $EDITOR (vi if neither is
+/// set) and read it back
+fn edit_in_editor(path: &Path, text: &str) -> Result<String, String> {
+    std::fs::write(path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    let editor = ["VISUAL", "EDITOR"].iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = std::process::Command::new(program).args(words).arg(path).status()
+        .map_err(|e| format!("Cannot start {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}, nothing changed", editor, status));
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+}
+
+// ── Globs shared by cb, find, rename and del ────────────────
+/// Shell-style patterns: `*`, `?`, `[abc]`, `[!a-z]`, `{a,b}` and `**` across directories.
+/// A pattern without `/` matches a name at any depth; one with `/` is anchored to the
+/// directory it's matched against; a trailing `/` only matches directories.
 mod glob {
-    pub fn glob(pattern: &str) -> Result<impl Iterator<Item = Result<std::path::PathBuf, ()>>, ()> {
-        let pat = pattern.to_string();
-        let base = std::path::Path::new(".");
-        let entries: Vec<_> = walkdir::WalkDir::new(base)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(move |e| {
-                let name = e.file_name().to_string_lossy().to_string();
-                super::glob_match(&pat, &name)
-                    || e.path().to_string_lossy().contains(pat.trim_matches('*'))
-            })
-            .map(|e| Ok(e.into_path()))
-            .collect();
-        Ok(entries.into_iter())
+    use std::path::{Path, PathBuf};
+
+    pub struct Pattern {
+        matcher: globset::GlobMatcher,
+        anchored: bool,
+        dir_only: bool,
+    }
+
+    impl Pattern {
+        pub fn new(pattern: &str, case_sensitive: bool) -> Result<Pattern, String> {
+            let mut p = pattern.strip_prefix("./").unwrap_or(pattern);
+            let dir_only = p.len() > 1 && p.ends_with('/');
+            p = p.trim_end_matches('/');
+            let anchored = p.contains('/');
+            p = p.trim_start_matches('/');
+            let matcher = globset::GlobBuilder::new(p)
+                .literal_separator(true)
+                .case_insensitive(!case_sensitive)
+                .empty_alternates(true)
+                .build()
+                .map_err(|e| format!("invalid glob '{pattern}': {}", e.kind()))?
+                .compile_matcher();
+            Ok(Pattern { matcher, anchored, dir_only })
+        }
+
+        /// `rel` is the path relative to where the pattern is anchored
+        pub fn matches(&self, rel: &Path, is_dir: bool) -> bool {
+            if self.dir_only && !is_dir { return false; }
+            if self.anchored {
+                self.matcher.is_match(rel)
+            } else {
+                rel.file_name().is_some_and(|n| self.matcher.is_match(n))
+            }
+        }
+    }
+
+    pub fn is_glob(s: &str) -> bool {
+        s.contains(['*', '?', '[', '{'])
+    }
+
+    /// Quote glob characters so `s` only matches itself
+    pub fn escape(s: &str) -> String {
+        s.chars().map(|c| match c {
+            '*' | '?' | '[' | ']' | '{' | '}' => format!("[{c}]"),
+            c => c.to_string(),
+        }).collect()
+    }
+
+    /// Expand a pattern against the file system, walking only below its literal prefix
+    /// (`src/**/*.rs` walks `src/`). Results are sorted.
+    pub fn glob(pattern: &str, case_sensitive: bool) -> Result<Vec<PathBuf>, String> {
+        let parts: Vec<&str> = pattern.split('/').collect();
+        let literal = parts.iter().take_while(|c| !is_glob(c)).count();
+        if literal == parts.len() {
+            return Ok(Path::new(pattern).exists().then(|| PathBuf::from(pattern)).into_iter().collect());
+        }
+        let base = match parts[..literal].join("/") {
+            b if b.is_empty() && pattern.starts_with('/') => "/".to_string(),
+            b if b.is_empty() => ".".to_string(),
+            b => b,
+        };
+        let rest = parts[literal..].join("/");
+        // below a literal prefix the rest is anchored to it: src/*.rs doesn't reach src/a/b.rs
+        let rest = if literal > 0 { format!("/{rest}") } else { rest };
+        let pat = Pattern::new(&rest, case_sensitive)?;
+
+        let base = PathBuf::from(base);
+        let mut out = Vec::new();
+        for entry in walkdir::WalkDir::new(&base).min_depth(1).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+            let rel = entry.path().strip_prefix(&base).unwrap_or(entry.path());
+            if pat.matches(rel, entry.file_type().is_dir()) {
+                out.push(if base == Path::new(".") { rel.to_path_buf() } else { entry.into_path() });
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// A fresh, empty directory under the system temp dir
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vasu-test-{}-{name}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn step(dir: &Path, from: &str, to: &str) -> RenameStep {
+        RenameStep { from: dir.join(from), to: dir.join(to) }
+    }
+
+    /// Play the moves against an in-memory directory, failing on any overwrite
+    fn simulate(files: &[&str], moves: &[(PathBuf, PathBuf, usize, bool)]) -> BTreeMap<String, String> {
+        let mut state: BTreeMap<String, String> = files.iter().map(|f| (f.to_string(), f.to_string())).collect();
+        for (from, to, _, _) in moves {
+            let from = from.file_name().unwrap().to_string_lossy().to_string();
+            let to = to.file_name().unwrap().to_string_lossy().to_string();
+            assert!(!state.contains_key(&to), "{from} → {to} would overwrite");
+            let content = state.remove(&from).unwrap_or_else(|| panic!("{from} doesn't exist"));
+            state.insert(to, content);
+        }
+        state
+    }
+
+    fn expect(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, content)| (name.to_string(), content.to_string())).collect()
+    }
+
+    #[test]
+    fn swap_goes_through_a_temp_name() {
+        let dir = Path::new("/x");
+        let moves = order_renames(&[step(dir, "a", "b"), step(dir, "b", "a")]);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(simulate(&["a", "b"], &moves), expect(&[("a", "b"), ("b", "a")]));
+    }
+
+    #[test]
+    fn three_cycle_rotates() {
+        let dir = Path::new("/x");
+        let plan = [step(dir, "a", "b"), step(dir, "b", "c"), step(dir, "c", "a")];
+        let moves = order_renames(&plan);
+        assert_eq!(moves.len(), 4);
+        assert_eq!(moves.iter().filter(|m| m.3).count(), 3);
+        assert_eq!(simulate(&["a", "b", "c"], &moves), expect(&[("a", "c"), ("b", "a"), ("c", "b")]));
+    }
+
+    #[test]
+    fn chain_moves_the_end_first() {
+        let dir = Path::new("/x");
+        let plan = [step(dir, "1", "2"), step(dir, "2", "3"), step(dir, "3", "4")];
+        let moves = order_renames(&plan);
+        assert_eq!(moves.len(), 3, "a chain needs no temp names");
+        assert_eq!(simulate(&["1", "2", "3"], &moves), expect(&[("2", "1"), ("3", "2"), ("4", "3")]));
+    }
+
+    #[test]
+    fn children_move_before_their_folder() {
+        let dir = Path::new("/x");
+        let plan = [step(dir, "d", "e"), step(&dir.join("d"), "f", "g")];
+        let moves = order_renames(&plan);
+        assert_eq!(moves[0].2, 1);
+        assert_eq!(moves[1].2, 0);
+    }
+
+    #[test]
+    fn clash_with_a_file_that_stays_is_refused() {
+        let dir = scratch("clash");
+        for f in ["a", "b", "keep"] {
+            std::fs::write(dir.join(f), f).unwrap();
+        }
+        // b is renamed away, so a → b is fine; keep isn't, so b → keep is not
+        let mut plan = vec![step(&dir, "a", "b"), step(&dir, "b", "keep")];
+        let problems = resolve_rename_conflicts(&mut plan, &dir, false);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("b  →  keep"), "{}", problems[0]);
+
+        let mut plan = vec![step(&dir, "a", "b"), step(&dir, "b", "keep")];
+        assert!(resolve_rename_conflicts(&mut plan, &dir, true).is_empty());
+        assert_eq!(plan[1].to, dir.join("keep_1"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn two_sources_for_one_target_clash() {
+        let dir = scratch("dup");
+        for f in ["x.TXT", "X.txt"] {
+            std::fs::write(dir.join(f), f).unwrap();
+        }
+        let mut plan = vec![step(&dir, "X.txt", "x.txt"), step(&dir, "x.TXT", "x.txt")];
+        let problems = resolve_rename_conflicts(&mut plan, &dir, false);
+        assert_eq!(problems, vec!["x.TXT  →  x.txt  (also the target of X.txt)".to_string()]);
+
+        let mut plan = vec![step(&dir, "X.txt", "x.txt"), step(&dir, "x.TXT", "x.txt")];
+        assert!(resolve_rename_conflicts(&mut plan, &dir, true).is_empty());
+        assert_eq!(plan[1].to, dir.join("x_1.txt"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// The parse tree as text, with each test reduced to what it checks
+    fn shape(p: &FindPred) -> String {
+        let list = |ps: &[FindPred]| ps.iter().map(shape).collect::<Vec<_>>().join(", ");
+        match p {
+            FindPred::Size(ord, n) => format!("size{}{n}", match ord {
+                std::cmp::Ordering::Greater => ">",
+                std::cmp::Ordering::Less => "<",
+                std::cmp::Ordering::Equal => "=",
+            }),
+            FindPred::Newer(_) => "newer".into(),
+            FindPred::Older(_) => "older".into(),
+            FindPred::Perm(how, bits) => format!("perm{how}{bits:o}"),
+            FindPred::Empty => "empty".into(),
+            FindPred::Regex(re) => format!("regex {re}"),
+            FindPred::Contains(_) => "contains".into(),
+            FindPred::Type(dir) => if *dir { "dir".into() } else { "file".into() },
+            FindPred::Name(_) => "name".into(),
+            FindPred::Not(p) => format!("not({})", shape(p)),
+            FindPred::And(ps) => format!("and({})", list(ps)),
+            FindPred::Or(ps) => format!("or({})", list(ps)),
+        }
+    }
+
+    fn parse_where(expr: &str) -> String {
+        shape(&FindExpr::parse(expr, false).unwrap_or_else(|e| panic!("{expr}: {e}")))
+    }
+
+    #[test]
+    fn where_and_binds_tighter_than_or() {
+        assert_eq!(parse_where("empty or type f and size +1K"), "or(empty, and(file, size>1024))");
+        assert_eq!(parse_where("type d || empty && perm 755"), "or(dir, and(empty, perm=755))");
+        // adjacent tests are and-ed without a keyword
+        assert_eq!(parse_where("type f size -1K"), "and(file, size<1024)");
+    }
+
+    #[test]
+    fn where_not_and_parentheses() {
+        assert_eq!(parse_where("not empty or type d"), "or(not(empty), dir)");
+        assert_eq!(parse_where("! (empty or type d)"), "not(or(empty, dir))");
+        assert_eq!(parse_where("(empty or type d) and perm /022"), "and(or(empty, dir), perm/22)");
+        assert_eq!(parse_where("((size 0))"), "size=0");
+        assert_eq!(parse_where("not not empty"), "not(not(empty))");
+    }
+
+    #[test]
+    fn where_quotes_keep_spaces() {
+        assert_eq!(find_expr_tokens(r#"contains "a b" or(regex 'x y')"#).unwrap(),
+            ["contains", "a b", "or", "(", "regex", "x y", ")"]);
+        assert_eq!(parse_where("regex '^a b$'"), "regex ^a b$");
+    }
+
+    #[test]
+    fn where_errors() {
+        for (expr, err) in [
+            ("", "--where is empty"),
+            ("(empty", "missing ')' in --where"),
+            ("empty )", "unexpected ')' in --where"),
+            ("empty or", "--where ended early"),
+            ("size", "'size' needs a value in --where"),
+            ("bogus 1", "unknown test 'bogus'"),
+            ("type x", "invalid type 'x' (f or d)"),
+            ("perm 9", "invalid mode '9' (try 644, -111, /022)"),
+        ] {
+            assert_eq!(FindExpr::parse(expr, false).err().as_deref(), Some(err), "{expr}");
+        }
+    }
+
+    #[test]
+    fn size_tests() {
+        assert_eq!(parse_where("size +10M"), format!("size>{}", 10 << 20));
+        assert_eq!(parse_where("size -1k"), "size<1024");
+        assert_eq!(parse_where("size 512"), "size=512");
+        assert_eq!(parse_where("size 1.5KB"), "size=1536");
+        assert!(FindPred::test("size", "+-1", false).is_err());
+        assert!(FindPred::test("size", "++1", false).is_err());
+        assert!(FindPred::test("size", "+", false).is_err());
+        assert!(FindPred::test("size", "10X", false).is_err());
+    }
+
+    #[test]
+    fn time_tests() {
+        let ago = |t: std::time::SystemTime| std::time::SystemTime::now().duration_since(t).unwrap().as_secs();
+        let Ok(FindPred::Newer(t)) = FindPred::test("newer", "2d", false) else { panic!("newer 2d") };
+        assert!((2 * 86400..2 * 86400 + 60).contains(&ago(t)));
+        let Ok(FindPred::Older(t)) = FindPred::test("older", "12h", false) else { panic!("older 12h") };
+        assert!((12 * 3600..12 * 3600 + 60).contains(&ago(t)));
+
+        // a date means local midnight at its start
+        let Ok(FindPred::Older(t)) = FindPred::test("older", "2026-01-31", false) else { panic!("older date") };
+        let t = chrono::DateTime::<chrono::Local>::from(t);
+        assert_eq!(t.naive_local(), chrono::NaiveDate::from_ymd_opt(2026, 1, 31).unwrap().and_hms_opt(0, 0, 0).unwrap());
+
+        for bad in ["2x", "yesterday", "2026-13-01", "-1d"] {
+            assert!(parse_when(bad).is_err(), "{bad}");
+        }
+    }
+
+    fn backup(series: &str, label: &str, when: &str) -> PruneItem {
+        let when = chrono::NaiveDateTime::parse_from_str(when, "%Y-%m-%d %H:%M:%S").unwrap();
+        PruneItem { series: series.into(), when, label: label.into(), size: 0, chunks: Vec::new() }
+    }
+
+    /// Labels that `policy` keeps, in input order
+    fn kept(items: &[PruneItem], policy: &RetentionArgs) -> Vec<String> {
+        let reasons = retention_reasons(items, policy);
+        items.iter().zip(&reasons).filter(|(_, r)| !r.is_empty()).map(|(i, _)| i.label.clone()).collect()
+    }
+
+    #[test]
+    fn retention_keeps_the_newest_per_bucket() {
+        let items = [
+            backup("s", "mon-a", "2026-03-02 09:00:00"),
+            backup("s", "mon-b", "2026-03-02 18:00:00"),
+            backup("s", "tue", "2026-03-03 09:00:00"),
+            backup("s", "feb", "2026-02-27 09:00:00"),
+            backup("s", "jan", "2026-01-15 09:00:00"),
+        ];
+        let daily = RetentionArgs { keep_daily: Some(2), ..Default::default() };
+        assert_eq!(kept(&items, &daily), ["mon-b", "tue"]);
+        // 2026-03-02 starts ISO week 10, so feb is the newest of week 9
+        let weekly = RetentionArgs { keep_weekly: Some(2), ..Default::default() };
+        assert_eq!(kept(&items, &weekly), ["tue", "feb"]);
+        let monthly = RetentionArgs { keep_monthly: Some(5), ..Default::default() };
+        assert_eq!(kept(&items, &monthly), ["tue", "feb", "jan"]);
+        let last = RetentionArgs { keep_last: Some(1), keep_monthly: Some(2), ..Default::default() };
+        let reasons = retention_reasons(&items, &last);
+        assert_eq!(reasons[2], ["last", "monthly"]);
+        assert_eq!(reasons[3], ["monthly"]);
+        assert!(reasons[0].is_empty() && reasons[1].is_empty() && reasons[4].is_empty());
+    }
+
+    #[test]
+    fn retention_counts_each_series_on_its_own() {
+        let items = [
+            backup("a", "a1", "2026-03-01 09:00:00"),
+            backup("b", "b1", "2026-03-02 09:00:00"),
+            backup("a", "a2", "2026-03-03 09:00:00"),
+            backup("b", "b2", "2026-03-04 09:00:00"),
+        ];
+        assert_eq!(kept(&items, &RetentionArgs { keep_last: Some(1), ..Default::default() }), ["a2", "b2"]);
+        let reasons = retention_reasons(&items, &RetentionArgs::default());
+        assert!(reasons.iter().all(|r| *r == ["no policy"]));
+    }
+
+    #[test]
+    fn retention_breaks_same_second_ties_by_id() {
+        // a second backup in the same second gets `_2`, a tenth `_10`
+        let items = [
+            backup("s", "20260302_090000_10", "2026-03-02 09:00:00"),
+            backup("s", "20260302_090000", "2026-03-02 09:00:00"),
+            backup("s", "20260302_090000_2", "2026-03-02 09:00:00"),
+            backup("s", "20260302_090000_9", "2026-03-02 09:00:00"),
+        ];
+        let daily = RetentionArgs { keep_daily: Some(1), ..Default::default() };
+        assert_eq!(kept(&items, &daily), ["20260302_090000_10"]);
+        let last = RetentionArgs { keep_last: Some(2), ..Default::default() };
+        assert_eq!(kept(&items, &last), ["20260302_090000_10", "20260302_090000_9"]);
+    }
+
+    #[test]
+    fn sizes_use_binary_units() {
+        assert_eq!(parse_size("1536"), Ok(1536));
+        assert_eq!(parse_size("500K"), Ok(500 << 10));
+        assert_eq!(parse_size("20mb"), Ok(20 << 20));
+        assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+        assert_eq!(parse_size(" 2 TiB "), Ok(2 << 40));
+        assert_eq!(parse_size("0"), Ok(0));
+        for bad in ["", "K", "-1M", "10X", "1..5G", "ten"] {
+            assert!(parse_size(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn drift_reports_changes_outside_the_backup_filter_only() {
+        use sha2::{Digest, Sha256};
+        let src = scratch("drift");
+        std::fs::create_dir_all(src.join("build")).unwrap();
+        for (name, content) in [("a.txt", "same"), ("b.txt", "edited"), ("d.txt", "new"), ("skip.log", "log"),
+                                ("build/out", "bin"), (".gitignore", "build/\n"), ("old.txt", "x")] {
+            std::fs::write(src.join(name), content).unwrap();
+        }
+        let hash = |s: &str| format!("{:x}", Sha256::digest(s));
+        let expected: BTreeMap<String, String> = [
+            ("a.txt", hash("same")),
+            ("b.txt", hash("before")),
+            ("c.txt", hash("gone")),
+            (".gitignore", hash("build/\n")),
+            // repo manifests written before whole-file hashes existed can't tell a modified file
+            ("old.txt", String::new()),
+        ].into_iter().map(|(n, h)| (n.to_string(), h)).collect();
+        let base = src.canonicalize().unwrap();
+
+        // b.txt modified, c.txt deleted, d.txt new; skip.log and build/ were never backed up
+        let filter = BackupFilter { exclude: vec!["*.log".into()], gitignore: true };
+        assert_eq!(report_drift(&expected, &src, &base, &filter), 3);
+        // without the filter they'd show up as new files
+        assert_eq!(report_drift(&expected, &src, &base, &BackupFilter::default()), 5);
+        std::fs::remove_dir_all(&src).ok();
+    }
+
+    #[test]
+    fn manifest_keeps_the_filter_out_of_the_file_list() {
+        let dir = scratch("manifest");
+        let filter = BackupFilter { exclude: vec!["*.log".into(), "tmp/".into()], gitignore: true };
+        let path = dir.join("src_20260301_090000.zip.sha256");
+        std::fs::write(&path, format!("{MANIFEST_FILTER}{}\nabc  src/a.txt\ndef  src/b c.txt\n",
+            serde_json::to_string(&filter).unwrap())).unwrap();
+        let files = read_manifest(&path).unwrap();
+        assert_eq!(files.into_iter().collect::<Vec<_>>(),
+            [("src/a.txt".to_string(), "abc".to_string()), ("src/b c.txt".to_string(), "def".to_string())]);
+        let read = read_manifest_filter(&path);
+        assert_eq!((read.exclude, read.gitignore), (filter.exclude, true));
+
+        // manifests from before the filter line mean "everything was backed up"
+        std::fs::write(&path, "abc  src/a.txt\n").unwrap();
+        let read = read_manifest_filter(&path);
+        assert!(read.exclude.is_empty() && !read.gitignore);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn toml(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn config_layers_merge_key_by_key() {
+        let global = toml("[zip]\nlevel = 9\nexclude = ['*.log']\n[size]\ntop = 5\n");
+        let project = toml("[zip]\nexclude = ['target/']\nstore = true\n");
+        let mut merged = global.clone();
+        merge_tables(&mut merged, project.clone());
+        assert_eq!(merged, toml("[zip]\nlevel = 9\nexclude = ['target/']\nstore = true\n[size]\ntop = 5\n"));
+
+        let cfg = build_config([global, project].into_iter()).unwrap();
+        assert_eq!(cfg.zip.level, Some(9));
+        assert_eq!(cfg.zip.exclude, ["target/"], "arrays are replaced, not appended");
+        assert!(cfg.zip.store && !cfg.zip.gitignore);
+        assert_eq!(cfg.size.top, 5);
+        assert_eq!(cfg.tree.depth, 4, "unset keys keep their defaults");
+    }
+
+    #[test]
+    fn config_errors_name_the_problem() {
+        let err = |text: &str| build_config([toml(text)].into_iter()).err().unwrap();
+        assert_eq!(err("[zip]\nlevel = 12\n"), "zip.level must be 0-9");
+        assert_eq!(err("[find]\ntype = 'x'\n"), "find.type must be f, d or all (got 'x')");
+        assert!(err("[zip]\nlevle = 1\n").contains("unknown field `levle`"));
+        assert!(err("[size]\ntop = 'many'\n").contains("invalid type"));
+        // only the merged result is validated, so the last layer's value is what counts
+        assert!(build_config([toml("[tree]\ndepth = 2\n"), toml("[tree]\ndepth = 0\n")].into_iter()).is_err());
+        assert!(build_config([toml("[tree]\ndepth = 0\n"), toml("[tree]\ndepth = 2\n")].into_iter()).is_ok());
+    }
+
+    #[test]
+    fn config_dest_is_relative_to_its_file() {
+        let dest = |text: &str, file: &str| {
+            let table = anchor_config_paths(toml(text), Path::new(file));
+            build_config([table].into_iter()).unwrap().backup.dest.unwrap()
+        };
+        assert_eq!(dest("[backup]\ndest = 'backups'\n", "/home/me/proj/.vasu.toml"), Path::new("/home/me/proj/backups"));
+        assert_eq!(dest("[backup]\ndest = '../b'\n", "/etc/vasu/config.toml"), Path::new("/etc/vasu/../b"));
+        assert_eq!(dest("[backup]\ndest = '/srv/b'\n", "/home/me/proj/.vasu.toml"), Path::new("/srv/b"));
+        // other tables are left alone
+        let table = anchor_config_paths(toml("[zip]\nexclude = ['a/']\n"), Path::new("/x/.vasu.toml"));
+        assert_eq!(table, toml("[zip]\nexclude = ['a/']\n"));
+    }
+
+    #[test]
+    fn glob_without_a_slash_matches_the_name_anywhere() {
+        let p = glob::Pattern::new("*.rs", true).unwrap();
+        assert!(p.matches(Path::new("main.rs"), false));
+        assert!(p.matches(Path::new("src/deep/lib.rs"), false));
+        assert!(!p.matches(Path::new("main.rs.bak"), false));
+    }
+
+    #[test]
+    fn glob_with_a_slash_is_anchored() {
+        let p = glob::Pattern::new("src/*.rs", true).unwrap();
+        assert!(p.matches(Path::new("src/main.rs"), false));
+        assert!(!p.matches(Path::new("src/bin/x.rs"), false), "* stops at /");
+        assert!(!p.matches(Path::new("lib/src/main.rs"), false));
+        // a leading ./ or / only anchors
+        for pat in ["./src/*.rs", "/src/*.rs"] {
+            assert!(glob::Pattern::new(pat, true).unwrap().matches(Path::new("src/main.rs"), false), "{pat}");
+        }
+        let p = glob::Pattern::new("src/**/*.rs", true).unwrap();
+        assert!(p.matches(Path::new("src/main.rs"), false));
+        assert!(p.matches(Path::new("src/a/b/c.rs"), false));
+    }
+
+    #[test]
+    fn glob_trailing_slash_means_directories_only() {
+        let p = glob::Pattern::new("target/", true).unwrap();
+        assert!(p.matches(Path::new("target"), true));
+        assert!(p.matches(Path::new("sub/target"), true), "still unanchored");
+        assert!(!p.matches(Path::new("target"), false));
+    }
+
+    #[test]
+    fn glob_case_and_escaping() {
+        let p = glob::Pattern::new("*.JPG", false).unwrap();
+        assert!(p.matches(Path::new("a.jpg"), false));
+        let p = glob::Pattern::new("*.JPG", true).unwrap();
+        assert!(!p.matches(Path::new("a.jpg"), false));
+        assert!(p.matches(Path::new("a.JPG"), false));
+
+        let p = glob::Pattern::new(&glob::escape("a[1]*{x}?.txt"), true).unwrap();
+        assert!(p.matches(Path::new("a[1]*{x}?.txt"), false));
+        assert!(!p.matches(Path::new("a1-x-.txt"), false));
+        assert!(glob::Pattern::new("*.{jpg,png}", true).unwrap().matches(Path::new("a.png"), false));
+        assert!(glob::Pattern::new("[", true).is_err());
     }
 }